@@ -0,0 +1,125 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::Pod;
+use solana_program::{entrypoint::ProgramResult, program_error::ProgramError};
+use crate::error::StablecoinError;
+
+pub const DISCRIMINATOR_LEN: usize = 8;
+/// Discriminator followed by the layout version byte.
+pub const HEADER_LEN: usize = DISCRIMINATOR_LEN + 1;
+
+fn check_header(
+    data: &[u8],
+    discriminator: &[u8; DISCRIMINATOR_LEN],
+    version: u8,
+) -> ProgramResult {
+    if data.len() < HEADER_LEN || data[..DISCRIMINATOR_LEN] != *discriminator {
+        return Err(StablecoinError::InvalidAccountType.into());
+    }
+    if data[DISCRIMINATOR_LEN] != version {
+        return Err(StablecoinError::UnsupportedAccountVersion.into());
+    }
+    Ok(())
+}
+
+/// State owned by this program. Each account starts with a fixed 8-byte type
+/// discriminator (the first bytes of `sha256("account:<TypeName>")`) and a
+/// layout version, followed by the Borsh-encoded body.
+pub trait ProgramAccount: BorshSerialize + BorshDeserialize {
+    const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN];
+    const VERSION: u8;
+
+    /// Decodes the account, rejecting other account types and layouts.
+    /// Trailing bytes past the body are ignored.
+    fn load(data: &[u8]) -> Result<Self, ProgramError> {
+        check_header(data, &Self::DISCRIMINATOR, Self::VERSION)?;
+        Ok(Self::deserialize(&mut &data[HEADER_LEN..])?)
+    }
+
+    /// Writes the header and body at the start of `data`.
+    fn store(&self, data: &mut [u8]) -> ProgramResult {
+        if data.len() < HEADER_LEN {
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+        data[..DISCRIMINATOR_LEN].copy_from_slice(&Self::DISCRIMINATOR);
+        data[DISCRIMINATOR_LEN] = Self::VERSION;
+        self.serialize(&mut &mut data[HEADER_LEN..])?;
+        Ok(())
+    }
+
+    /// Returns the full account image, header included.
+    fn to_account_data(&self) -> Result<Vec<u8>, ProgramError> {
+        let mut data = Self::DISCRIMINATOR.to_vec();
+        data.push(Self::VERSION);
+        self.serialize(&mut data)?;
+        Ok(data)
+    }
+}
+
+/// Fixed-layout state cast directly over the account data instead of being
+/// deserialized. Implementors are `#[repr(C)]` and begin with a discriminator
+/// array and version byte so they share the Borsh accounts' header.
+pub trait ZeroCopyAccount: Pod {
+    const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN];
+    const VERSION: u8;
+    const LEN: usize = std::mem::size_of::<Self>();
+
+    fn load(data: &[u8]) -> Result<&Self, ProgramError> {
+        check_header(data, &Self::DISCRIMINATOR, Self::VERSION)?;
+        data.get(..Self::LEN)
+            .and_then(|bytes| bytemuck::try_from_bytes(bytes).ok())
+            .ok_or_else(|| StablecoinError::InvalidAccountType.into())
+    }
+
+    fn load_mut(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        check_header(data, &Self::DISCRIMINATOR, Self::VERSION)?;
+        data.get_mut(..Self::LEN)
+            .and_then(|bytes| bytemuck::try_from_bytes_mut(bytes).ok())
+            .ok_or_else(|| StablecoinError::InvalidAccountType.into())
+    }
+
+    /// Zeroes a freshly allocated account, writes the header and returns the
+    /// state for the caller to fill in.
+    fn init(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        let bytes = data.get_mut(..Self::LEN).ok_or(ProgramError::AccountDataTooSmall)?;
+        bytes.fill(0);
+        bytes[..DISCRIMINATOR_LEN].copy_from_slice(&Self::DISCRIMINATOR);
+        bytes[DISCRIMINATOR_LEN] = Self::VERSION;
+        Self::load_mut(data)
+    }
+}
+
+/// Returns the layout version if `data` holds an account of type `T`.
+pub fn account_version<T: ProgramAccount>(data: &[u8]) -> Option<u8> {
+    (data.len() >= HEADER_LEN && data[..DISCRIMINATOR_LEN] == T::DISCRIMINATOR)
+        .then(|| data[DISCRIMINATOR_LEN])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AllowlistEntry, Position};
+    use solana_program::pubkey::Pubkey;
+
+    #[test]
+    fn test_account_header_round_trip() {
+        let entry = AllowlistEntry {
+            config: Pubkey::new_unique(),
+            wallet: Pubkey::new_unique(),
+        };
+        let data = entry.to_account_data().unwrap();
+        assert_eq!(data.len(), AllowlistEntry::LEN);
+        assert_eq!(account_version::<AllowlistEntry>(&data), Some(AllowlistEntry::VERSION));
+
+        let loaded = AllowlistEntry::load(&data).unwrap();
+        assert_eq!(loaded.wallet, entry.wallet);
+
+        // Another account type's bytes are rejected
+        assert!(Position::load(&data).is_err());
+        assert_eq!(account_version::<Position>(&data), None);
+
+        // As is an unknown layout version
+        let mut future = data.clone();
+        future[DISCRIMINATOR_LEN] = AllowlistEntry::VERSION + 1;
+        assert!(AllowlistEntry::load(&future).is_err());
+    }
+}
@@ -57,6 +57,48 @@ pub enum StablecoinError {
     PriceUncertain,
     #[error("Invalid oracle price feed")]
     InvalidOraclePriceFeed,
+    #[error("Fee exempt list full")]
+    FeeExemptListFull,
+    #[error("Output below minimum (slippage exceeded)")]
+    SlippageExceeded,
+    #[error("Wallet is blocked")]
+    WalletBlocked,
+    #[error("Blocklist full")]
+    BlocklistFull,
+    #[error("Token account is not frozen")]
+    AccountNotFrozen,
+    #[error("Clawback requires a Token-2022 permanent delegate")]
+    ClawbackUnsupported,
+    #[error("Transfer not allowed between these wallets")]
+    TransferNotAllowed,
+    #[error("Recorded supply does not match the mint")]
+    SupplyMismatch,
+    #[error("Debt ceiling exceeded")]
+    DebtCeilingExceeded,
+    #[error("Collateral type is disabled")]
+    CollateralTypeDisabled,
+    #[error("Config is already initialized")]
+    AlreadyInitialized,
+    #[error("Account is not of the expected type")]
+    InvalidAccountType,
+    #[error("Unsupported account layout version")]
+    UnsupportedAccountVersion,
+    #[error("Field exceeds maximum length")]
+    FieldTooLong,
+    #[error("Stablecoin has been shut down")]
+    ShutDown,
+    #[error("Stablecoin has not been shut down")]
+    NotShutDown,
+    #[error("Every shut-down collateral type must be provided exactly once")]
+    IncompleteCollateralTypes,
+    #[error("Stablecoin supply is still outstanding")]
+    SupplyOutstanding,
+    #[error("Stablecoin is in emergency settlement")]
+    EmergencySettlement,
+    #[error("Wallet is not allowlisted")]
+    NotAllowlisted,
+    #[error("Position owners have not finished settling")]
+    SettlementOutstanding,
 }
 
 impl From<StablecoinError> for ProgramError {
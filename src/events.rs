@@ -0,0 +1,414 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{entrypoint::ProgramResult, log::sol_log_data, pubkey::Pubkey};
+use crate::{account::DISCRIMINATOR_LEN, AuthorityType};
+
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+/// Structured record emitted through `sol_log_data` as an 8-byte discriminator
+/// (the first bytes of `sha256("event:<TypeName>")`) followed by the Borsh body.
+/// Discriminators never change, and fields are only ever appended.
+pub trait Event: BorshSerialize {
+    const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN];
+
+    fn emit(&self) -> ProgramResult {
+        let mut data = Self::DISCRIMINATOR.to_vec();
+        self.serialize(&mut data)?;
+        sol_log_data(&[&data]);
+        Ok(())
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Minted {
+    pub config: Pubkey,
+    pub minter: Pubkey,
+    pub recipient: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub collateral_amount: u64,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+impl Event for Minted {
+    const DISCRIMINATOR: [u8; 8] = [0xae, 0x83, 0x15, 0x39, 0x58, 0x75, 0x72, 0x79];
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Redeemed {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub amount: u64,
+    pub collateral_amount: u64,
+    pub fee: u64,
+}
+
+impl Event for Redeemed {
+    const DISCRIMINATOR: [u8; 8] = [0x0e, 0x1d, 0xb7, 0x47, 0x1f, 0xa5, 0x6b, 0x26];
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct AuthorityChanged {
+    pub config: Pubkey,
+    pub authority_type: AuthorityType,
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
+impl Event for AuthorityChanged {
+    const DISCRIMINATOR: [u8; 8] = [0x1f, 0x13, 0xae, 0x98, 0x04, 0x52, 0xd7, 0xe2];
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Paused {
+    pub config: Pubkey,
+    pub pause_flags: u8,
+}
+
+impl Event for Paused {
+    const DISCRIMINATOR: [u8; 8] = [0xac, 0xf8, 0x05, 0xfd, 0x31, 0xff, 0xff, 0xe8];
+}
+
+/// Oracle price accepted for a collateral type.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct PriceUpdated {
+    pub config: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub price: f64,
+}
+
+impl Event for PriceUpdated {
+    const DISCRIMINATOR: [u8; 8] = [0x9a, 0x48, 0x57, 0x96, 0xf6, 0xe6, 0x17, 0xd9];
+}
+
+/// Fees paid to the fee collector: stability fees in stablecoin, or a
+/// collateral type's redemption fees in that collateral.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct FeeCollected {
+    pub config: Pubkey,
+    pub fee_collector: Pubkey,
+    pub amount: u64,
+    /// `None` for stability fees.
+    pub collateral_mint: Option<Pubkey>,
+}
+
+impl Event for FeeCollected {
+    const DISCRIMINATOR: [u8; 8] = [0x0c, 0x1c, 0x11, 0xf8, 0xf4, 0x24, 0x08, 0x49];
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Repaid {
+    pub config: Pubkey,
+    pub owner: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub amount: u64,
+}
+
+impl Event for Repaid {
+    const DISCRIMINATOR: [u8; 8] = [0x26, 0xf8, 0xe7, 0x07, 0x96, 0xa4, 0xac, 0x17];
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Withdrawn {
+    pub config: Pubkey,
+    pub owner: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub collateral_amount: u64,
+}
+
+impl Event for Withdrawn {
+    const DISCRIMINATOR: [u8; 8] = [0x14, 0x59, 0xdf, 0xc6, 0xc2, 0x7c, 0xdb, 0x0d];
+}
+
+/// Tokens burned from a frozen account and re-issued to the treasury.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Seized {
+    pub config: Pubkey,
+    pub compliance_authority: Pubkey,
+    pub source: Pubkey,
+    pub owner: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+}
+
+impl Event for Seized {
+    const DISCRIMINATOR: [u8; 8] = [0xc5, 0x30, 0xcb, 0xcb, 0xae, 0x25, 0x64, 0x41];
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ShutDown {
+    pub config: Pubkey,
+    pub emergency: bool,
+    pub collateral_types: u8,
+    pub total_supply: u64,
+}
+
+impl Event for ShutDown {
+    const DISCRIMINATOR: [u8; 8] = [0x68, 0x8a, 0x79, 0xec, 0x6a, 0x92, 0xeb, 0x25];
+}
+
+/// Collateral paid from one settlement for tokens redeemed after a shutdown.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SettlementClaimed {
+    pub config: Pubkey,
+    pub holder: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub amount: u64,
+    pub collateral_amount: u64,
+}
+
+impl Event for SettlementClaimed {
+    const DISCRIMINATOR: [u8; 8] = [0x55, 0xd7, 0x26, 0x1f, 0x7f, 0x32, 0xe6, 0x4a];
+}
+
+/// Any event this program emits, as decoded by indexers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StablecoinEvent {
+    Minted(Minted),
+    Redeemed(Redeemed),
+    AuthorityChanged(AuthorityChanged),
+    Paused(Paused),
+    PriceUpdated(PriceUpdated),
+    FeeCollected(FeeCollected),
+    Repaid(Repaid),
+    Withdrawn(Withdrawn),
+    Seized(Seized),
+    ShutDown(ShutDown),
+    SettlementClaimed(SettlementClaimed),
+}
+
+impl StablecoinEvent {
+    /// Decodes the bytes of one `sol_log_data` field. Returns `None` for
+    /// unknown discriminators; bytes past the known fields are ignored.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        fn body<T: Event + BorshDeserialize>(data: &[u8]) -> Option<T> {
+            T::deserialize(&mut &data[DISCRIMINATOR_LEN..]).ok()
+        }
+
+        let discriminator: [u8; DISCRIMINATOR_LEN] = data.get(..DISCRIMINATOR_LEN)?.try_into().ok()?;
+        match discriminator {
+            Minted::DISCRIMINATOR => body(data).map(Self::Minted),
+            Redeemed::DISCRIMINATOR => body(data).map(Self::Redeemed),
+            AuthorityChanged::DISCRIMINATOR => body(data).map(Self::AuthorityChanged),
+            Paused::DISCRIMINATOR => body(data).map(Self::Paused),
+            PriceUpdated::DISCRIMINATOR => body(data).map(Self::PriceUpdated),
+            FeeCollected::DISCRIMINATOR => body(data).map(Self::FeeCollected),
+            Repaid::DISCRIMINATOR => body(data).map(Self::Repaid),
+            Withdrawn::DISCRIMINATOR => body(data).map(Self::Withdrawn),
+            Seized::DISCRIMINATOR => body(data).map(Self::Seized),
+            ShutDown::DISCRIMINATOR => body(data).map(Self::ShutDown),
+            SettlementClaimed::DISCRIMINATOR => body(data).map(Self::SettlementClaimed),
+            _ => None,
+        }
+    }
+
+    /// Extracts the events emitted by `program_id` from a transaction's log
+    /// messages, in order. Invocations are tracked so data logged by other
+    /// programs, including ones this program calls, is never misattributed.
+    pub fn parse_logs<S: AsRef<str>>(program_id: &Pubkey, logs: &[S]) -> Vec<Self> {
+        let mut invocations: Vec<Pubkey> = Vec::new();
+        let mut events = Vec::new();
+
+        for log in logs {
+            let log = log.as_ref();
+            if let Some(data) = log.strip_prefix(PROGRAM_DATA_PREFIX) {
+                if invocations.last() != Some(program_id) {
+                    continue;
+                }
+                let decoded = data
+                    .split(' ')
+                    .filter_map(|field| STANDARD.decode(field).ok())
+                    .filter_map(|bytes| Self::decode(&bytes));
+                events.extend(decoded);
+                continue;
+            }
+
+            match parse_frame_change(log) {
+                Some(FrameChange::Invoke(program)) => invocations.push(program),
+                // An exit only closes the frame of the program that opened it
+                Some(FrameChange::Exit(program)) if invocations.last() == Some(&program) => {
+                    invocations.pop();
+                }
+                _ => {}
+            }
+        }
+
+        events
+    }
+}
+
+enum FrameChange {
+    Invoke(Pubkey),
+    Exit(Pubkey),
+}
+
+/// Recognises only the runtime's own `Program <id> invoke [n]`,
+/// `Program <id> success` and `Program <id> failed: …` lines. Program output
+/// such as `Program log: …` never starts with a valid program id, so it cannot
+/// open or close a frame.
+fn parse_frame_change(log: &str) -> Option<FrameChange> {
+    let mut words = log.strip_prefix("Program ")?.splitn(3, ' ');
+    let program = words.next()?.parse::<Pubkey>().ok()?;
+    match (words.next()?, words.next()) {
+        ("invoke", Some(depth)) if depth.starts_with('[') && depth.ends_with(']') => {
+            Some(FrameChange::Invoke(program))
+        }
+        ("success", None) => Some(FrameChange::Exit(program)),
+        ("failed:", Some(_)) => Some(FrameChange::Exit(program)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode<T: Event>(event: &T) -> Vec<u8> {
+        let mut data = T::DISCRIMINATOR.to_vec();
+        event.serialize(&mut data).unwrap();
+        data
+    }
+
+    fn program_data<T: Event>(event: &T) -> String {
+        format!("{}{}", PROGRAM_DATA_PREFIX, STANDARD.encode(encode(event)))
+    }
+
+    #[test]
+    fn test_decode_round_trip() {
+        let event = FeeCollected {
+            config: Pubkey::new_unique(),
+            fee_collector: Pubkey::new_unique(),
+            amount: 42,
+            collateral_mint: None,
+        };
+        let data = encode(&event);
+
+        assert_eq!(StablecoinEvent::decode(&data), Some(StablecoinEvent::FeeCollected(event)));
+        assert_eq!(StablecoinEvent::decode(&data[..4]), None);
+        assert_eq!(StablecoinEvent::decode(&[0u8; 48]), None);
+    }
+
+    #[test]
+    fn test_decode_every_event() {
+        let config = Pubkey::new_unique();
+        let collateral_mint = Pubkey::new_unique();
+        let events = vec![
+            StablecoinEvent::Repaid(Repaid {
+                config,
+                owner: Pubkey::new_unique(),
+                collateral_mint,
+                amount: 1_000,
+            }),
+            StablecoinEvent::Withdrawn(Withdrawn {
+                config,
+                owner: Pubkey::new_unique(),
+                collateral_mint,
+                collateral_amount: 2_000,
+            }),
+            StablecoinEvent::Seized(Seized {
+                config,
+                compliance_authority: Pubkey::new_unique(),
+                source: Pubkey::new_unique(),
+                owner: Pubkey::new_unique(),
+                destination: Pubkey::new_unique(),
+                amount: 3_000,
+            }),
+            StablecoinEvent::ShutDown(ShutDown {
+                config,
+                emergency: true,
+                collateral_types: 2,
+                total_supply: 4_000,
+            }),
+            StablecoinEvent::SettlementClaimed(SettlementClaimed {
+                config,
+                holder: Pubkey::new_unique(),
+                collateral_mint,
+                amount: 500,
+                collateral_amount: 250,
+            }),
+            StablecoinEvent::FeeCollected(FeeCollected {
+                config,
+                fee_collector: Pubkey::new_unique(),
+                amount: 6,
+                collateral_mint: Some(collateral_mint),
+            }),
+        ];
+
+        let program_id = Pubkey::new_unique();
+        let mut logs = vec![format!("Program {} invoke [1]", program_id)];
+        for event in &events {
+            let data = match event {
+                StablecoinEvent::Repaid(event) => encode(event),
+                StablecoinEvent::Withdrawn(event) => encode(event),
+                StablecoinEvent::Seized(event) => encode(event),
+                StablecoinEvent::ShutDown(event) => encode(event),
+                StablecoinEvent::SettlementClaimed(event) => encode(event),
+                StablecoinEvent::FeeCollected(event) => encode(event),
+                _ => unreachable!(),
+            };
+            assert_eq!(StablecoinEvent::decode(&data).as_ref(), Some(event));
+            logs.push(format!("{}{}", PROGRAM_DATA_PREFIX, STANDARD.encode(&data)));
+        }
+        logs.push(format!("Program {} success", program_id));
+
+        assert_eq!(StablecoinEvent::parse_logs(&program_id, &logs), events);
+    }
+
+    #[test]
+    fn test_parse_logs_only_attributes_own_invocations() {
+        let event = FeeCollected {
+            config: Pubkey::new_unique(),
+            fee_collector: Pubkey::new_unique(),
+            amount: 42,
+            collateral_mint: Some(Pubkey::new_unique()),
+        };
+        let program_id = Pubkey::new_unique();
+        let other_program = Pubkey::new_unique();
+        let logs = vec![
+            format!("Program {} invoke [1]", program_id),
+            format!("Program {} invoke [2]", other_program),
+            program_data(&event),
+            format!("Program {} consumed 100 of 200000 compute units", other_program),
+            format!("Program {} success", other_program),
+            program_data(&event),
+            format!("Program {} failed: custom program error: 0x1", program_id),
+            program_data(&event),
+        ];
+
+        let events = StablecoinEvent::parse_logs(&program_id, &logs);
+        assert_eq!(events, vec![StablecoinEvent::FeeCollected(event)]);
+    }
+
+    #[test]
+    fn test_parse_logs_ignores_spoofed_frames() {
+        let event = FeeCollected {
+            config: Pubkey::new_unique(),
+            fee_collector: Pubkey::new_unique(),
+            amount: 7,
+            collateral_mint: None,
+        };
+        let program_id = Pubkey::new_unique();
+        let other_program = Pubkey::new_unique();
+        let logs = vec![
+            format!("Program {} invoke [1]", program_id),
+            format!("Program {} invoke [2]", other_program),
+            // A called program logging text cannot close its own frame
+            "Program log: success".to_string(),
+            format!("Program log: {} success", other_program),
+            program_data(&event),
+            // ...nor pretend to open one for this program
+            format!("Program log: {} invoke [3]", program_id),
+            program_data(&event),
+            format!("Program return: {} AQ==", other_program),
+            format!("Program {} success", other_program),
+            // Nor can an unmatched exit pop this program's frame
+            format!("Program {} success", other_program),
+            program_data(&event),
+            format!("Program {} success", program_id),
+        ];
+
+        let events = StablecoinEvent::parse_logs(&program_id, &logs);
+        assert_eq!(events, vec![StablecoinEvent::FeeCollected(event)]);
+    }
+}
@@ -0,0 +1,108 @@
+use solana_program::{entrypoint::ProgramResult, program_error::ProgramError};
+use crate::{error::StablecoinError, CollateralType, StablecoinState};
+
+pub const SECONDS_PER_YEAR: f64 = 31_536_000.0;
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Compounds the collateral type's annualized stability fee into its cumulative
+/// rate index up to `now` and books the fees accrued on its outstanding debt
+/// since the last update into the stablecoin state.
+pub fn accrue_stability_fees(
+    collateral_type: &mut CollateralType,
+    state: &mut StablecoinState,
+    now: i64,
+) -> ProgramResult {
+    let elapsed = now - collateral_type.last_fee_accrual_timestamp;
+    if elapsed <= 0 {
+        return Ok(());
+    }
+
+    let growth = (1.0 + collateral_type.stability_fee_rate).powf(elapsed as f64 / SECONDS_PER_YEAR);
+    let new_index = collateral_type.cumulative_rate_index * growth;
+
+    let fees = (collateral_type.total_normalized_debt as f64
+        * (new_index - collateral_type.cumulative_rate_index)) as u64;
+    state.accrued_fees = state.accrued_fees.checked_add(fees)
+        .ok_or(StablecoinError::InvalidAmount)?;
+    collateral_type.cumulative_rate_index = new_index;
+    collateral_type.last_fee_accrual_timestamp = now;
+
+    Ok(())
+}
+
+/// Converts a debt amount in stablecoin units into index-scaled units.
+pub fn normalize_debt(amount: u64, rate_index: f64) -> u64 {
+    (amount as f64 / rate_index).ceil() as u64
+}
+
+/// Converts index-scaled debt back into stablecoin units at the given index.
+pub fn denormalize_debt(normalized_debt: u64, rate_index: f64) -> u64 {
+    (normalized_debt as f64 * rate_index) as u64
+}
+
+/// Returns the flat basis-point fee charged on `amount`, rounded down.
+pub fn calculate_fee(amount: u64, fee_bps: u16) -> Result<u64, ProgramError> {
+    let fee = (amount as u128 * fee_bps as u128) / BPS_DENOMINATOR as u128;
+    u64::try_from(fee).map_err(|_| StablecoinError::InvalidAmount.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+    use solana_program::pubkey::Pubkey;
+
+    fn collateral_type(stability_fee_rate: f64, total_normalized_debt: u64) -> CollateralType {
+        CollateralType {
+            config: Pubkey::new_unique(),
+            collateral_mint: Pubkey::new_unique(),
+            oracle: Pubkey::new_unique(),
+            vault: Pubkey::new_unique(),
+            decimals: 6,
+            loan_to_value: 0.5,
+            liquidation_threshold: 0.8,
+            stability_fee_rate,
+            cumulative_rate_index: 1.0,
+            last_fee_accrual_timestamp: 0,
+            total_normalized_debt,
+            debt_ceiling: u64::MAX,
+            debt: total_normalized_debt,
+            accrued_redemption_fees: 0,
+            last_oracle_price: 1.0,
+            is_enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_debt_normalization() {
+        let rate_index = 1.05;
+
+        let normalized = normalize_debt(1_000_000, rate_index);
+        let debt = denormalize_debt(normalized, rate_index);
+
+        // Rounding always favours the protocol
+        assert!(debt >= 1_000_000);
+        assert!(debt - 1_000_000 <= 2);
+    }
+
+    #[test]
+    fn test_calculate_fee() {
+        assert_eq!(calculate_fee(1_000_000, 30).unwrap(), 3_000);
+        assert_eq!(calculate_fee(999, 10).unwrap(), 0);
+        assert_eq!(calculate_fee(u64::MAX, 10_000).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn test_accrue_stability_fees() {
+        let mut collateral_type = collateral_type(0.05, 1_000_000);
+        let mut state = StablecoinState::zeroed();
+
+        accrue_stability_fees(&mut collateral_type, &mut state, SECONDS_PER_YEAR as i64).unwrap();
+        assert!((collateral_type.cumulative_rate_index - 1.05).abs() < 1e-9);
+        assert!((49_999..=50_000).contains(&state.accrued_fees));
+
+        // Accruing again at the same timestamp books nothing
+        accrue_stability_fees(&mut collateral_type, &mut state, SECONDS_PER_YEAR as i64).unwrap();
+        assert!((49_999..=50_000).contains(&state.accrued_fees));
+    }
+}
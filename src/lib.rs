@@ -1,18 +1,59 @@
 use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
-    entrypoint,
     entrypoint::ProgramResult,
     msg,
+    program::{invoke, invoke_signed},
     program_error::ProgramError,
+    program_pack::Pack,
     pubkey::Pubkey,
-    sysvar::{clock::Clock, Sysvar},
+    system_instruction, system_program,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
 };
-use spl_token::instruction::{burn, mint_to};
+use spl_token::instruction::{close_account, transfer};
+use spl_tlv_account_resolution::state::ExtraAccountMetaList;
+use spl_token_2022::{
+    extension::{
+        transfer_hook::instruction as transfer_hook_instruction, ExtensionType,
+        StateWithExtensions,
+    },
+    instruction::{
+        burn, freeze_account, initialize_mint2, initialize_permanent_delegate, mint_to,
+        thaw_account,
+    },
+};
+use spl_transfer_hook_interface::{
+    collect_extra_account_metas_signer_seeds, get_extra_account_metas_address_and_bump_seed,
+    instruction::{ExecuteInstruction, TransferHookInstruction},
+};
+use std::cell::{Ref, RefMut};
 
+mod account;
 mod error;
+pub mod events;
+mod fees;
+mod metadata;
 mod oracle;
-use crate::{error::StablecoinError, oracle::{get_oracle_price, OraclePrice}};
+mod transfer_hook;
+use crate::{
+    account::{account_version, ProgramAccount, ZeroCopyAccount, HEADER_LEN},
+    error::StablecoinError,
+    events::{
+        AuthorityChanged, Event, FeeCollected, Minted, Paused, PriceUpdated, Redeemed, Repaid, Seized,
+        SettlementClaimed, ShutDown, Withdrawn,
+    },
+    fees::{accrue_stability_fees, calculate_fee, denormalize_debt, normalize_debt},
+    metadata::{
+        create_metaplex_metadata, initialize_metadata_pointer, initialize_token_metadata,
+        token_metadata_len, update_metaplex_metadata, update_token_metadata,
+        validate_metaplex_accounts,
+    },
+    oracle::{get_oracle_price, OraclePrice},
+    transfer_hook::{
+        extra_account_metas, find_allowlist_entry_address, is_allowlisted, ALLOWLIST_SEED,
+    },
+};
 
 // Program ID
 solana_program::declare_id!("StbcYYHXFR8nqG7YwvLbKBrpYPE4XbG65gKxGHu4vKP");
@@ -22,26 +63,344 @@ const ORACLE_STALENESS_THRESHOLD: i64 = 300; // 5 minutes
 const PRICE_CONF_PERCENTAGE: f64 = 0.01; // 1% confidence interval
 const MAX_PRICE_DEVIATION: f64 = 0.05; // 5%
 const MINT_LIMIT: u64 = 1_000_000;
+const MAX_STABILITY_FEE_RATE: f64 = 1.0; // 100% per year
+const CONFIG_SEED: &[u8] = b"config";
+const STATE_SEED: &[u8] = b"state";
+const METADATA_SEED: &[u8] = b"metadata";
+const ROLES_SEED: &[u8] = b"roles";
+const TREASURY_SEED: &[u8] = b"treasury";
+const POSITION_SEED: &[u8] = b"position";
+const VAULT_AUTHORITY_SEED: &[u8] = b"vault";
+const MINT_AUTHORITY_SEED: &[u8] = b"mint_authority";
+const FREEZE_AUTHORITY_SEED: &[u8] = b"freeze_authority";
+const PERMANENT_DELEGATE_SEED: &[u8] = b"permanent_delegate";
+const STABLECOIN_DECIMALS: u8 = 6;
+const MAX_FEE_BPS: u16 = 1_000; // 10%
+const MAX_FEE_EXEMPT_ACCOUNTS: usize = 16;
+const DEFAULT_REDEMPTION_PRICE: f64 = 1.0;
+const DEFAULT_MIN_REDEMPTION_RATE: f64 = 0.98; // 2% below peg
+const BLOCKLIST_SEED: &[u8] = b"blocklist";
+const MAX_BLOCKLIST_ENTRIES: usize = 256;
+const COLLATERAL_TYPE_SEED: &[u8] = b"collateral";
+const SETTLEMENT_SEED: &[u8] = b"settlement";
+const SETTLEMENT_CLAIM_PERIOD: i64 = 365 * 24 * 60 * 60; // 1 year
+const MAX_NAME_LEN: usize = 32;
+const MAX_SYMBOL_LEN: usize = 10;
+const MAX_URI_LEN: usize = 200;
+const MAX_TARGET_CURRENCY_LEN: usize = 8;
+const MAX_AUTHORIZED_MINTERS: usize = 16;
 
+// Bits of `StablecoinState::pause_flags`, each halting one kind of operation
+pub const PAUSE_MINT: u8 = 1 << 0;
+pub const PAUSE_REDEEM: u8 = 1 << 1;
+pub const PAUSE_LIQUIDATE: u8 = 1 << 2;
+pub const PAUSE_TRANSFER_HOOK: u8 = 1 << 3;
+pub const PAUSE_DEPOSIT: u8 = 1 << 4;
+pub const PAUSE_WITHDRAW: u8 = 1 << 5;
+pub const PAUSE_ALL: u8 = PAUSE_MINT
+    | PAUSE_REDEEM
+    | PAUSE_LIQUIDATE
+    | PAUSE_TRANSFER_HOOK
+    | PAUSE_DEPOSIT
+    | PAUSE_WITHDRAW;
+
+/// Immutable wiring of a deployment: the token mint, its program and the PDAs
+/// that control it. Names, authorities and treasury settings live in the
+/// metadata, roles and treasury accounts so instructions only lock what they
+/// touch.
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct StablecoinConfig {
+    pub is_initialized: bool,
+    pub mint_authority: Pubkey,
+    pub oracle_pubkey: Pubkey,
+    pub collateral_ratio: u64,
+    pub token_mint: Pubkey,
+    pub freeze_authority: Pubkey,
+    pub token_program: Pubkey,
+    pub permanent_delegate: Option<Pubkey>,
+    pub kyc_mode: bool,
+}
+
+impl StablecoinConfig {
+    pub const LEN: usize = HEADER_LEN
+        + 1 // is_initialized
+        + 32 * 2 // mint_authority, oracle_pubkey
+        + 8 // collateral_ratio
+        + 32 * 3 // token_mint, freeze_authority, token_program
+        + 1 + 32 // permanent_delegate
+        + 1; // kyc_mode
+}
+
+// Earlier layouts also held the metadata, roles and treasury settings, and
+// version 1 the live accounting; MigrateConfig splits them out
+impl ProgramAccount for StablecoinConfig {
+    const DISCRIMINATOR: [u8; 8] = [0x7f, 0x19, 0xf4, 0xd5, 0x01, 0xc0, 0x65, 0x06];
+    const VERSION: u8 = 3;
+}
+
+/// Accounts hanging off a config, each recording the config it belongs to.
+trait ConfigAccount: ProgramAccount {
+    fn config(&self) -> &Pubkey;
+}
+
+/// Display metadata, only touched by metadata updates.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct StablecoinMetadata {
+    pub config: Pubkey,
     pub name: String,
     pub symbol: String,
     pub icon_uri: String,
     pub target_currency: String,
-    pub mint_authority: Pubkey,
-    pub oracle_pubkey: Pubkey,
-    pub total_supply: u64,
-    pub collateral_ratio: u64,
-    pub last_oracle_price: f64,
-    pub last_update_timestamp: i64,
+}
+
+impl StablecoinMetadata {
+    pub const LEN: usize = HEADER_LEN
+        + 32
+        + 4 + MAX_NAME_LEN
+        + 4 + MAX_SYMBOL_LEN
+        + 4 + MAX_URI_LEN
+        + 4 + MAX_TARGET_CURRENCY_LEN;
+}
+
+impl ProgramAccount for StablecoinMetadata {
+    const DISCRIMINATOR: [u8; 8] = [0xd5, 0x37, 0xf3, 0x08, 0x41, 0x11, 0x2b, 0x97];
+    const VERSION: u8 = 1;
+}
+
+impl ConfigAccount for StablecoinMetadata {
+    fn config(&self) -> &Pubkey {
+        &self.config
+    }
+}
+
+/// Authorities allowed to administer the deployment and mint against it.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct Roles {
+    pub config: Pubkey,
     pub admin_authority: Pubkey,
     pub oracle_authority: Pubkey,
-    pub fee_collector: Pubkey,
-    pub is_paused: bool,
+    pub compliance_authority: Pubkey,
     pub authorized_minters: Vec<Pubkey>,
 }
 
+impl Roles {
+    pub const LEN: usize = HEADER_LEN + 32 * 4 + 4 + 32 * MAX_AUTHORIZED_MINTERS;
+}
+
+impl ProgramAccount for Roles {
+    const DISCRIMINATOR: [u8; 8] = [0xb1, 0x25, 0x11, 0xc9, 0xf2, 0x9e, 0xd4, 0x41];
+    const VERSION: u8 = 1;
+}
+
+impl ConfigAccount for Roles {
+    fn config(&self) -> &Pubkey {
+        &self.config
+    }
+}
+
+/// Where fees and seized funds go, and who is exempt from fees.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct Treasury {
+    pub config: Pubkey,
+    pub fee_collector: Pubkey,
+    pub wallet: Pubkey,
+    pub fee_exempt_accounts: Vec<Pubkey>,
+}
+
+impl Treasury {
+    pub const LEN: usize = HEADER_LEN + 32 * 3 + 4 + 32 * MAX_FEE_EXEMPT_ACCOUNTS;
+}
+
+impl ProgramAccount for Treasury {
+    const DISCRIMINATOR: [u8; 8] = [0xee, 0xef, 0x7b, 0xee, 0x59, 0x01, 0xa8, 0xfd];
+    const VERSION: u8 = 1;
+}
+
+impl ConfigAccount for Treasury {
+    fn config(&self) -> &Pubkey {
+        &self.config
+    }
+}
+
+/// Live accounting and risk parameters touched on every mint and redeem. Kept
+/// apart from the config and read in place so the hot path never decodes or
+/// rewrites the config's strings and lists.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct StablecoinState {
+    pub discriminator: [u8; 8],
+    pub version: u8,
+    /// `PAUSE_*` bits for the operations currently halted.
+    pub pause_flags: u8,
+    pub is_shut_down: u8,
+    /// Set alongside `is_shut_down` when the shutdown settles at fixed prices.
+    pub is_emergency_shutdown: u8,
+    /// Collateral types frozen by `Shutdown`, all of which each post-shutdown
+    /// redemption and the final close must cover.
+    pub shutdown_collateral_types: u8,
+    /// Collateral types registered by `AddCollateralType`; a shutdown must
+    /// freeze every one of them.
+    pub collateral_types: u8,
+    pub _padding: [u8; 2],
+    pub config: Pubkey,
+    pub total_supply: u64,
+    pub accrued_fees: u64,
+    pub debt_ceiling: u64,
+    pub last_update_timestamp: i64,
+    pub redemption_price: f64,
+    pub min_redemption_rate: f64,
+    pub mint_fee_bps: u16,
+    pub redeem_fee_bps: u16,
+    pub _reserved: [u8; 4],
+}
+
+impl StablecoinState {
+    /// Fails if any operation in `flags` is paused.
+    pub fn ensure_not_paused(&self, flags: u8) -> ProgramResult {
+        if self.pause_flags & flags != 0 {
+            return Err(StablecoinError::ProgramPaused.into());
+        }
+        Ok(())
+    }
+}
+
+impl ZeroCopyAccount for StablecoinState {
+    const DISCRIMINATOR: [u8; 8] = [0x6b, 0x21, 0x86, 0x36, 0x81, 0x0d, 0xbb, 0x97];
+    const VERSION: u8 = 1;
+}
+
+/// Registry entry for one accepted collateral asset, holding its oracle, vault
+/// and risk parameters along with the debt issued against it.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct CollateralType {
+    pub config: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub oracle: Pubkey,
+    pub vault: Pubkey,
+    pub decimals: u8,
+    pub loan_to_value: f64,
+    pub liquidation_threshold: f64,
+    pub stability_fee_rate: f64,
+    pub cumulative_rate_index: f64,
+    pub last_fee_accrual_timestamp: i64,
+    pub total_normalized_debt: u64,
+    pub debt_ceiling: u64,
+    pub debt: u64,
+    pub accrued_redemption_fees: u64,
+    pub last_oracle_price: f64,
+    pub is_enabled: bool,
+}
+
+impl CollateralType {
+    pub const LEN: usize = HEADER_LEN + 32 * 4 + 1 + 8 * 10 + 1;
+}
+
+impl ProgramAccount for CollateralType {
+    const DISCRIMINATOR: [u8; 8] = [0xe1, 0x68, 0xe7, 0xf6, 0x24, 0x74, 0xf4, 0x68];
+    const VERSION: u8 = 1;
+}
+
+/// Final terms for one collateral type after an emergency shutdown, fixed at
+/// the last price the program accepted for it.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct Settlement {
+    pub config: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub vault: Pubkey,
+    pub final_price: f64,
+    /// Collateral paid out per stablecoin base unit claimed.
+    pub collateral_per_token: f64,
+    /// Share of each position's debt the holders are owed, below 1 when
+    /// positions owe more than the supply.
+    pub coverage: f64,
+    /// Collateral still set aside for stablecoin holders.
+    pub holder_collateral: u64,
+    /// Excess collateral still owed back to position owners.
+    pub owner_collateral: u64,
+    pub settled_at: i64,
+}
+
+impl Settlement {
+    pub const LEN: usize = HEADER_LEN + 32 * 3 + 8 * 6;
+}
+
+impl ProgramAccount for Settlement {
+    const DISCRIMINATOR: [u8; 8] = [0x37, 0x0b, 0xdb, 0x21, 0x24, 0x88, 0x28, 0xb6];
+    const VERSION: u8 = 1;
+}
+
+impl ConfigAccount for Settlement {
+    fn config(&self) -> &Pubkey {
+        &self.config
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct CollateralParameters {
+    pub loan_to_value: f64,
+    pub liquidation_threshold: f64,
+    pub stability_fee_rate: f64,
+    pub debt_ceiling: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct Position {
+    pub owner: Pubkey,
+    pub config: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub collateral_amount: u64,
+    pub normalized_debt: u64,
+}
+
+impl Position {
+    pub const LEN: usize = HEADER_LEN + 32 + 32 + 32 + 8 + 8;
+}
+
+impl ProgramAccount for Position {
+    const DISCRIMINATOR: [u8; 8] = [0xaa, 0xbc, 0x8f, 0xe4, 0x7a, 0x40, 0xf7, 0xd0];
+    const VERSION: u8 = 1;
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct Blocklist {
+    pub config: Pubkey,
+    pub blocked_wallets: Vec<Pubkey>,
+}
+
+impl Blocklist {
+    // Allocated at full capacity up front so entries never require a realloc
+    pub const LEN: usize = HEADER_LEN + 32 + 4 + 32 * MAX_BLOCKLIST_ENTRIES;
+}
+
+impl ProgramAccount for Blocklist {
+    const DISCRIMINATOR: [u8; 8] = [0xd8, 0xc6, 0x7b, 0xd9, 0x52, 0x53, 0x39, 0x02];
+    const VERSION: u8 = 1;
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct AllowlistEntry {
+    pub config: Pubkey,
+    pub wallet: Pubkey,
+}
+
+impl AllowlistEntry {
+    pub const LEN: usize = HEADER_LEN + 32 + 32;
+}
+
+impl ProgramAccount for AllowlistEntry {
+    const DISCRIMINATOR: [u8; 8] = [0x2a, 0x3b, 0x58, 0x01, 0x7c, 0x8a, 0x5c, 0xec];
+    const VERSION: u8 = 1;
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum AuthorityType {
+    Admin,
+    Oracle,
+    FeeCollector,
+    Compliance,
+    Treasury,
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub enum StablecoinInstruction {
     Initialize {
@@ -52,30 +411,260 @@ pub enum StablecoinInstruction {
     },
     Mint {
         amount: u64,
+        min_out: u64,
     },
+    /// Burns `amount` against the debt of the given position and pays out
+    /// the collateral it releases. Owners may redeem against their own
+    /// positions at any time; anyone else only against positions past their
+    /// collateral type's liquidation threshold, so holders deleverage the
+    /// riskiest positions first and healthy ones are never redeemed against.
     Redeem {
         amount: u64,
+        min_out: u64,
+    },
+    Repay {
+        amount: u64,
+    },
+    CollectFees,
+    UpdateFees {
+        mint_fee_bps: u16,
+        redeem_fee_bps: u16,
+    },
+    AddFeeExemption {
+        account: Pubkey,
+    },
+    RemoveFeeExemption {
+        account: Pubkey,
+    },
+    UpdateRedemptionParameters {
+        redemption_price: f64,
+        min_redemption_rate: f64,
+    },
+    UpdateMetadata {
+        name: String,
+        symbol: String,
+        icon_uri: String,
+    },
+    CreateMetaplexMetadata,
+    UpdateAuthority {
+        authority_type: AuthorityType,
+        new_authority: Pubkey,
+    },
+    Freeze,
+    Thaw,
+    BlockWallet {
+        wallet: Pubkey,
+    },
+    UnblockWallet {
+        wallet: Pubkey,
+    },
+    /// Burns `amount` from a frozen account through the permanent delegate
+    /// and re-issues it to the treasury. Only Token-2022 mints have a
+    /// permanent delegate; on classic SPL Token mints this always fails with
+    /// `ClawbackUnsupported`, and freezing is the strongest remedy available.
+    Seize {
+        amount: u64,
+    },
+    InitializeTransferHook,
+    SetKycMode {
+        enabled: bool,
+    },
+    AddToAllowlist {
+        wallet: Pubkey,
+    },
+    RemoveFromAllowlist {
+        wallet: Pubkey,
+    },
+    Reconcile,
+    UpdateDebtCeiling {
+        debt_ceiling: u64,
+    },
+    AddCollateralType {
+        params: CollateralParameters,
+    },
+    UpdateCollateralType {
+        params: CollateralParameters,
+    },
+    DisableCollateralType,
+    MigrateConfig,
+    Shutdown,
+    ShutdownRedeem {
+        amount: u64,
+    },
+    CloseDeployment,
+    EmergencyShutdown,
+    SettlePosition,
+    ClaimSettlement {
+        amount: u64,
+    },
+    SetPauseFlags {
+        pause_flags: u8,
+    },
+    UpdateTransferHookAccounts,
+    CollectRedemptionFees,
+    Withdraw {
+        amount: u64,
     },
 }
 
-entrypoint!(process_instruction);
+/// Returns the config PDA for the stablecoin issued as `token_mint`.
+pub fn find_config_address(program_id: &Pubkey, token_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CONFIG_SEED, token_mint.as_ref()], program_id)
+}
+
+/// Returns the metadata PDA belonging to `config`.
+pub fn find_metadata_address(program_id: &Pubkey, config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[METADATA_SEED, config.as_ref()], program_id)
+}
+
+/// Returns the roles PDA belonging to `config`.
+pub fn find_roles_address(program_id: &Pubkey, config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ROLES_SEED, config.as_ref()], program_id)
+}
+
+/// Returns the treasury PDA belonging to `config`.
+pub fn find_treasury_address(program_id: &Pubkey, config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[TREASURY_SEED, config.as_ref()], program_id)
+}
+
+/// Returns the hot-state PDA belonging to `config`.
+pub fn find_state_address(program_id: &Pubkey, config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[STATE_SEED, config.as_ref()], program_id)
+}
+
+/// Returns the emergency settlement PDA for one collateral type of `config`.
+pub fn find_settlement_address(program_id: &Pubkey, config: &Pubkey, collateral_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[SETTLEMENT_SEED, config.as_ref(), collateral_mint.as_ref()], program_id)
+}
+
+// The macro tests cfgs that only the Solana toolchain defines
+#[allow(unexpected_cfgs)]
+mod entry {
+    use super::process_instruction;
+    solana_program::entrypoint!(process_instruction);
+}
 
 pub fn process_instruction(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
+    // Token-2022 calls back into the program through the transfer-hook interface
+    if let Ok(TransferHookInstruction::Execute { amount }) = TransferHookInstruction::unpack(instruction_data) {
+        return process_transfer_hook_execute(program_id, accounts, amount);
+    }
+
     let instruction = StablecoinInstruction::try_from_slice(instruction_data)?;
 
     match instruction {
         StablecoinInstruction::Initialize { name, symbol, icon_uri, target_currency } => {
             process_initialize(program_id, accounts, name, symbol, icon_uri, target_currency)
         }
-        StablecoinInstruction::Mint { amount } => {
-            process_mint(program_id, accounts, amount)
+        StablecoinInstruction::Mint { amount, min_out } => {
+            process_mint(program_id, accounts, amount, min_out)
+        }
+        StablecoinInstruction::Redeem { amount, min_out } => {
+            process_redeem(program_id, accounts, amount, min_out)
+        }
+        StablecoinInstruction::Repay { amount } => {
+            process_repay(program_id, accounts, amount)
+        }
+        StablecoinInstruction::CollectFees => {
+            process_collect_fees(program_id, accounts)
+        }
+        StablecoinInstruction::UpdateFees { mint_fee_bps, redeem_fee_bps } => {
+            process_update_fees(program_id, accounts, mint_fee_bps, redeem_fee_bps)
+        }
+        StablecoinInstruction::AddFeeExemption { account } => {
+            process_add_fee_exemption(program_id, accounts, account)
+        }
+        StablecoinInstruction::RemoveFeeExemption { account } => {
+            process_remove_fee_exemption(program_id, accounts, account)
+        }
+        StablecoinInstruction::UpdateRedemptionParameters { redemption_price, min_redemption_rate } => {
+            process_update_redemption_parameters(program_id, accounts, redemption_price, min_redemption_rate)
+        }
+        StablecoinInstruction::UpdateMetadata { name, symbol, icon_uri } => {
+            process_update_metadata(program_id, accounts, name, symbol, icon_uri)
         }
-        StablecoinInstruction::Redeem { amount } => {
-            process_redeem(program_id, accounts, amount)
+        StablecoinInstruction::CreateMetaplexMetadata => {
+            process_create_metaplex_metadata(program_id, accounts)
+        }
+        StablecoinInstruction::UpdateAuthority { authority_type, new_authority } => {
+            process_update_authority(program_id, accounts, authority_type, new_authority)
+        }
+        StablecoinInstruction::Freeze => {
+            process_freeze(program_id, accounts, true)
+        }
+        StablecoinInstruction::Thaw => {
+            process_freeze(program_id, accounts, false)
+        }
+        StablecoinInstruction::BlockWallet { wallet } => {
+            process_block_wallet(program_id, accounts, wallet)
+        }
+        StablecoinInstruction::UnblockWallet { wallet } => {
+            process_unblock_wallet(program_id, accounts, wallet)
+        }
+        StablecoinInstruction::Seize { amount } => {
+            process_seize(program_id, accounts, amount)
+        }
+        StablecoinInstruction::InitializeTransferHook => {
+            process_initialize_transfer_hook(program_id, accounts)
+        }
+        StablecoinInstruction::SetKycMode { enabled } => {
+            process_set_kyc_mode(program_id, accounts, enabled)
+        }
+        StablecoinInstruction::AddToAllowlist { wallet } => {
+            process_add_to_allowlist(program_id, accounts, wallet)
+        }
+        StablecoinInstruction::RemoveFromAllowlist { wallet } => {
+            process_remove_from_allowlist(program_id, accounts, wallet)
+        }
+        StablecoinInstruction::Reconcile => {
+            process_reconcile(program_id, accounts)
+        }
+        StablecoinInstruction::UpdateDebtCeiling { debt_ceiling } => {
+            process_update_debt_ceiling(program_id, accounts, debt_ceiling)
+        }
+        StablecoinInstruction::AddCollateralType { params } => {
+            process_add_collateral_type(program_id, accounts, params)
+        }
+        StablecoinInstruction::UpdateCollateralType { params } => {
+            process_update_collateral_type(program_id, accounts, params)
+        }
+        StablecoinInstruction::DisableCollateralType => {
+            process_disable_collateral_type(program_id, accounts)
+        }
+        StablecoinInstruction::MigrateConfig => {
+            process_migrate_config(program_id, accounts)
+        }
+        StablecoinInstruction::Shutdown => {
+            process_shutdown(program_id, accounts)
+        }
+        // Both shutdowns settle the same way; holders redeem through either
+        StablecoinInstruction::ShutdownRedeem { amount } | StablecoinInstruction::ClaimSettlement { amount } => {
+            process_claim_settlement(program_id, accounts, amount)
+        }
+        StablecoinInstruction::CloseDeployment => {
+            process_close_deployment(program_id, accounts)
+        }
+        StablecoinInstruction::EmergencyShutdown => {
+            process_emergency_shutdown(program_id, accounts)
+        }
+        StablecoinInstruction::SettlePosition => {
+            process_settle_position(program_id, accounts)
+        }
+        StablecoinInstruction::SetPauseFlags { pause_flags } => {
+            process_set_pause_flags(program_id, accounts, pause_flags)
+        }
+        StablecoinInstruction::UpdateTransferHookAccounts => {
+            process_update_transfer_hook_accounts(program_id, accounts)
+        }
+        StablecoinInstruction::CollectRedemptionFees => {
+            process_collect_redemption_fees(program_id, accounts)
+        }
+        StablecoinInstruction::Withdraw { amount } => {
+            process_withdraw(program_id, accounts, amount)
         }
     }
 }
@@ -90,33 +679,236 @@ fn process_initialize(
 ) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
     let config_account = next_account_info(accounts_iter)?;
-    let mint_authority = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
     let oracle_account = next_account_info(accounts_iter)?;
+    let token_mint = next_account_info(accounts_iter)?;
+    let mint_authority = next_account_info(accounts_iter)?;
+    let freeze_authority = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+    let coin_metadata_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let treasury_account = next_account_info(accounts_iter)?;
 
-    // Verify the account is owned by our program
-    if config_account.owner != program_id {
+    // The config lives at a PDA derived from the mint it governs, and every
+    // other deployment account at a PDA derived from the config
+    let (config_pda, config_bump) = find_config_address(program_id, token_mint.key);
+    let (state_pda, state_bump) = find_state_address(program_id, config_account.key);
+    let (coin_metadata_pda, coin_metadata_bump) = find_metadata_address(program_id, config_account.key);
+    let (roles_pda, roles_bump) = find_roles_address(program_id, config_account.key);
+    let (treasury_pda, treasury_bump) = find_treasury_address(program_id, config_account.key);
+    if config_pda != *config_account.key
+        || state_pda != *state_account.key
+        || coin_metadata_pda != *coin_metadata_account.key
+        || roles_pda != *roles_account.key
+        || treasury_pda != *treasury_account.key
+    {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // The initializer becomes every authority and pays for the accounts
+    if !admin.is_signer {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    validate_metadata_lengths(&name, &symbol, &icon_uri)?;
+    if target_currency.len() > MAX_TARGET_CURRENCY_LEN {
+        return Err(StablecoinError::FieldTooLong.into());
+    }
+
+    // A config can only ever be initialized once
+    if StablecoinConfig::load(&config_account.data.borrow()).is_ok_and(|config| config.is_initialized) {
+        return Err(StablecoinError::AlreadyInitialized.into());
+    }
+
+    let (mint_authority_pda, mint_authority_bump) = Pubkey::find_program_address(
+        &[MINT_AUTHORITY_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+    let (freeze_authority_pda, _) = Pubkey::find_program_address(
+        &[FREEZE_AUTHORITY_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+    if mint_authority_pda != *mint_authority.key || freeze_authority_pda != *freeze_authority.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let (permanent_delegate_pda, _) = Pubkey::find_program_address(
+        &[PERMANENT_DELEGATE_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+
+    // Token-2022 mints carry their metadata on-chain via extensions, give the
+    // program a permanent delegate for court-ordered clawbacks and leave room
+    // for the KYC transfer hook
+    let is_token_2022 = *token_program.key == spl_token_2022::id();
+    if !is_token_2022 && *token_program.key != spl_token::id() {
         return Err(ProgramError::IncorrectProgramId);
     }
 
+    // Create the stablecoin mint with program-held mint and freeze authorities.
+    // Rent must already cover the metadata written after initialization.
+    let rent = Rent::get()?;
+    let (mint_space, mint_lamports) = if is_token_2022 {
+        let space = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(
+            &[
+                ExtensionType::MetadataPointer,
+                ExtensionType::PermanentDelegate,
+                ExtensionType::TransferHook,
+            ],
+        )?;
+        let metadata_len = token_metadata_len(token_mint.key, &mint_authority_pda, &name, &symbol, &icon_uri)?;
+        (space, rent.minimum_balance(space + metadata_len))
+    } else {
+        let space = spl_token_2022::state::Mint::LEN;
+        (space, rent.minimum_balance(space))
+    };
+
+    invoke(
+        &system_instruction::create_account(
+            admin.key,
+            token_mint.key,
+            mint_lamports,
+            mint_space as u64,
+            token_program.key,
+        ),
+        &[admin.clone(), token_mint.clone(), system_program.clone()],
+    )?;
+
+    if is_token_2022 {
+        initialize_metadata_pointer(token_program, token_mint, &mint_authority_pda)?;
+        invoke(
+            &initialize_permanent_delegate(token_program.key, token_mint.key, &permanent_delegate_pda)?,
+            std::slice::from_ref(token_mint),
+        )?;
+        // The hook program is only attached once KYC mode is switched on
+        invoke(
+            &transfer_hook_instruction::initialize(
+                token_program.key,
+                token_mint.key,
+                Some(mint_authority_pda),
+                None,
+            )?,
+            std::slice::from_ref(token_mint),
+        )?;
+    }
+
+    invoke(
+        &initialize_mint2(
+            token_program.key,
+            token_mint.key,
+            &mint_authority_pda,
+            Some(&freeze_authority_pda),
+            STABLECOIN_DECIMALS,
+        )?,
+        std::slice::from_ref(token_mint),
+    )?;
+
+    if is_token_2022 {
+        initialize_token_metadata(
+            token_program,
+            token_mint,
+            mint_authority,
+            &[MINT_AUTHORITY_SEED, config_account.key.as_ref(), &[mint_authority_bump]],
+            name.clone(),
+            symbol.clone(),
+            icon_uri.clone(),
+        )?;
+    }
+
     let config = StablecoinConfig {
+        is_initialized: true,
+        mint_authority: mint_authority_pda,
+        oracle_pubkey: *oracle_account.key,
+        collateral_ratio: 0,
+        token_mint: *token_mint.key,
+        freeze_authority: freeze_authority_pda,
+        token_program: *token_program.key,
+        permanent_delegate: is_token_2022.then_some(permanent_delegate_pda),
+        kyc_mode: false,
+    };
+    create_program_account(
+        program_id,
+        admin,
+        config_account,
+        system_program,
+        StablecoinConfig::LEN,
+        &[CONFIG_SEED, token_mint.key.as_ref(), &[config_bump]],
+    )?;
+    config.store(&mut config_account.data.borrow_mut())?;
+
+    let coin_metadata = StablecoinMetadata {
+        config: *config_account.key,
         name,
         symbol,
         icon_uri,
         target_currency,
-        mint_authority: *mint_authority.key,
-        oracle_pubkey: *oracle_account.key,
-        total_supply: 0,
-        collateral_ratio: 0,
-        last_oracle_price: 0.0,
-        last_update_timestamp: 0,
-        admin_authority: *mint_authority.key,
+    };
+    create_program_account(
+        program_id,
+        admin,
+        coin_metadata_account,
+        system_program,
+        StablecoinMetadata::LEN,
+        &[METADATA_SEED, config_account.key.as_ref(), &[coin_metadata_bump]],
+    )?;
+    coin_metadata.store(&mut coin_metadata_account.data.borrow_mut())?;
+
+    let roles = Roles {
+        config: *config_account.key,
+        admin_authority: *admin.key,
         oracle_authority: *oracle_account.key,
-        fee_collector: *mint_authority.key,
-        is_paused: false,
-        authorized_minters: vec![*mint_authority.key],
+        compliance_authority: *admin.key,
+        authorized_minters: vec![*admin.key],
+    };
+    create_program_account(
+        program_id,
+        admin,
+        roles_account,
+        system_program,
+        Roles::LEN,
+        &[ROLES_SEED, config_account.key.as_ref(), &[roles_bump]],
+    )?;
+    roles.store(&mut roles_account.data.borrow_mut())?;
+
+    let treasury = Treasury {
+        config: *config_account.key,
+        fee_collector: *admin.key,
+        wallet: *admin.key,
+        fee_exempt_accounts: vec![],
     };
+    create_program_account(
+        program_id,
+        admin,
+        treasury_account,
+        system_program,
+        Treasury::LEN,
+        &[TREASURY_SEED, config_account.key.as_ref(), &[treasury_bump]],
+    )?;
+    treasury.store(&mut treasury_account.data.borrow_mut())?;
+
+    create_program_account(
+        program_id,
+        admin,
+        state_account,
+        system_program,
+        StablecoinState::LEN,
+        &[STATE_SEED, config_account.key.as_ref(), &[state_bump]],
+    )?;
+    let mut state_data = state_account.data.borrow_mut();
+    let state = StablecoinState::init(&mut state_data)?;
+    state.config = *config_account.key;
+    state.debt_ceiling = u64::MAX;
+    state.redemption_price = DEFAULT_REDEMPTION_PRICE;
+    state.min_redemption_rate = DEFAULT_MIN_REDEMPTION_RATE;
+
+    Ok(())
+}
 
-    config.serialize(&mut *config_account.data.borrow_mut())?;
+fn validate_metadata_lengths(name: &str, symbol: &str, icon_uri: &str) -> ProgramResult {
+    if name.len() > MAX_NAME_LEN || symbol.len() > MAX_SYMBOL_LEN || icon_uri.len() > MAX_URI_LEN {
+        return Err(StablecoinError::FieldTooLong.into());
+    }
     Ok(())
 }
 
@@ -142,43 +934,148 @@ fn validate_price(
     Ok(())
 }
 
+/// Scale between collateral and stablecoin base units.
+fn decimal_scale(collateral_decimals: u8) -> f64 {
+    10f64.powi(STABLECOIN_DECIMALS as i32 - collateral_decimals as i32)
+}
+
+/// Values `amount` of collateral in stablecoin base units at `price`
+/// (collateral per stablecoin).
+fn collateral_to_stablecoin(amount: u64, price: f64, collateral_decimals: u8) -> u64 {
+    (amount as f64 / price * decimal_scale(collateral_decimals)) as u64
+}
+
+/// Returns the collateral owed for `amount` stablecoin base units at `price`.
+fn stablecoin_to_collateral(amount: u64, price: f64, collateral_decimals: u8) -> u64 {
+    (amount as f64 * price / decimal_scale(collateral_decimals)) as u64
+}
+
+/// Whether `debt` exceeds the collateral type's liquidation threshold of the
+/// value of `collateral` at `price`.
+fn past_liquidation_threshold(debt: u64, collateral: u64, price: f64, collateral_type: &CollateralType) -> bool {
+    let value = collateral_to_stablecoin(collateral, price, collateral_type.decimals);
+    debt as f64 > value as f64 * collateral_type.liquidation_threshold
+}
+
+/// Returns the collateral released for redeeming `amount` against a position
+/// holding `position_collateral` for `position_debt`: the collateral worth the
+/// redemption price per token, or the position's own backing if that is less.
+/// Fails when the backing is worth less than the minimum redemption rate of
+/// the redemption price, so a crash in the collateral cannot be redeemed at a
+/// loss beyond the floor.
+fn redemption_collateral(
+    amount: u64,
+    price: f64,
+    collateral_decimals: u8,
+    state: &StablecoinState,
+    position_collateral: u64,
+    position_debt: u64,
+) -> Result<u64, ProgramError> {
+    let at_redemption_price = stablecoin_to_collateral(amount, price * state.redemption_price, collateral_decimals);
+    let floor = stablecoin_to_collateral(
+        amount,
+        price * state.redemption_price * state.min_redemption_rate,
+        collateral_decimals,
+    );
+    let backing = (position_collateral as u128 * amount as u128 / position_debt as u128) as u64;
+    if backing < floor {
+        return Err(StablecoinError::BelowRedemptionPrice.into());
+    }
+
+    Ok(at_redemption_price.min(backing))
+}
+
 fn process_mint(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     amount: u64,
+    min_out: u64,
 ) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
     let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let treasury_account = next_account_info(accounts_iter)?;
     let token_mint = next_account_info(accounts_iter)?;
     let user_token_account = next_account_info(accounts_iter)?;
     let oracle_account = next_account_info(accounts_iter)?;
-    let mint_authority = next_account_info(accounts_iter)?;
+    let minter = next_account_info(accounts_iter)?;
     let clock_sysvar = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let fee_collector_token_account = next_account_info(accounts_iter)?;
+    let mint_authority = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let blocklist_account = next_account_info(accounts_iter)?;
+    let collateral_type_account = next_account_info(accounts_iter)?;
+    let source_collateral_account = next_account_info(accounts_iter)?;
+    let collateral_vault = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+    let recipient_allowlist_entry = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
 
-    let mut config = StablecoinConfig::try_from_slice(&config_account.data.borrow())?;
+    let config = StablecoinConfig::load(&config_account.data.borrow())?;
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+    let treasury: Treasury = load_config_account(program_id, config_account, treasury_account)?;
+    let mut state = load_state_mut(program_id, config_account, state_account)?;
 
-    // Check program is not paused
-    if config.is_paused {
-        return Err(StablecoinError::ProgramPaused.into());
+    // Minting also deposits collateral, so either pause halts it
+    state.ensure_not_paused(PAUSE_MINT | PAUSE_DEPOSIT)?;
+    if state.is_shut_down != 0 {
+        return Err(StablecoinError::ShutDown.into());
     }
 
-    // Verify mint authority
-    if !mint_authority.is_signer || !config.authorized_minters.contains(mint_authority.key) {
+    // Verify minter
+    if !minter.is_signer || !roles.authorized_minters.contains(minter.key) {
         return Err(StablecoinError::UnauthorizedMinter.into());
     }
 
-    // Check mint limit
-    if amount > MINT_LIMIT {
-        return Err(StablecoinError::MintLimitExceeded.into());
-    }
+    // Verify token accounts belong to this stablecoin
+    validate_token_program(token_program, &config)?;
+    validate_token_mint(token_mint, &config)?;
+    sync_total_supply(&mut state, token_mint)?;
+    let user_token = unpack_stablecoin_account(user_token_account, &config)?;
 
-    // Get and validate price
+    ensure_not_blocked(
+        program_id,
+        config_account,
+        blocklist_account,
+        &[minter.key, &user_token.owner],
+    )?;
+
+    // In KYC mode new tokens may only be issued to allowlisted wallets
+    if config.kyc_mode
+        && !is_allowlisted(program_id, config_account.key, &user_token.owner, recipient_allowlist_entry)
+    {
+        return Err(StablecoinError::NotAllowlisted.into());
+    }
+
+    // New debt can only be drawn against enabled collateral types
+    let mut collateral_type = load_collateral_type(program_id, config_account, collateral_type_account)?;
+    if !collateral_type.is_enabled {
+        return Err(StablecoinError::CollateralTypeDisabled.into());
+    }
+    if *oracle_account.key != collateral_type.oracle {
+        return Err(StablecoinError::InvalidOraclePriceFeed.into());
+    }
+    if *collateral_vault.key != collateral_type.vault {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    // Check mint limit
+    if amount > MINT_LIMIT {
+        return Err(StablecoinError::MintLimitExceeded.into());
+    }
+
+    // Get and validate price
     let current_price = get_oracle_price(oracle_account)?;
     let oracle_data = OraclePrice::try_from_slice(&oracle_account.data.borrow())?;
     
     validate_price(
         current_price,
-        config.last_oracle_price,
+        collateral_type.last_oracle_price,
         oracle_data.confidence,
     )?;
 
@@ -188,37 +1085,128 @@ fn process_mint(
         return Err(StablecoinError::StaleOracleData.into());
     }
 
-    // Calculate tokens to mint based on price
-    let tokens_to_mint = (amount as f64 / current_price) as u64;
+    accrue_stability_fees(&mut collateral_type, &mut state, clock.unix_timestamp)?;
 
-    // Mint tokens to user account
-    let mint_ix = mint_to(
+    // Lend against the deposited collateral at the type's loan-to-value
+    let collateral_value = collateral_to_stablecoin(amount, current_price, collateral_type.decimals);
+    let tokens_to_mint = (collateral_value as f64 * collateral_type.loan_to_value) as u64;
+
+    // Check global and per-collateral debt ceilings
+    let new_total_supply = state.total_supply.checked_add(tokens_to_mint)
+        .ok_or(StablecoinError::InvalidAmount)?;
+    let new_collateral_debt = collateral_type.debt.checked_add(tokens_to_mint)
+        .ok_or(StablecoinError::InvalidAmount)?;
+    if new_total_supply > state.debt_ceiling || new_collateral_debt > collateral_type.debt_ceiling {
+        return Err(StablecoinError::DebtCeilingExceeded.into());
+    }
+
+    // Record the deposit and minted debt on the minter's position
+    let mut position = load_or_create_position(
+        program_id,
+        config_account,
+        &collateral_type,
+        position_account,
+        minter,
+        system_program,
+    )?;
+    let normalized_amount = normalize_debt(tokens_to_mint, collateral_type.cumulative_rate_index);
+    position.collateral_amount = position.collateral_amount.checked_add(amount)
+        .ok_or(StablecoinError::InvalidAmount)?;
+    position.normalized_debt = position.normalized_debt.checked_add(normalized_amount)
+        .ok_or(StablecoinError::InvalidAmount)?;
+    collateral_type.total_normalized_debt = collateral_type.total_normalized_debt.checked_add(normalized_amount)
+        .ok_or(StablecoinError::InvalidAmount)?;
+
+    // The whole position must stay within the loan-to-value at the current price
+    let position_debt = denormalize_debt(position.normalized_debt, collateral_type.cumulative_rate_index);
+    let position_value = collateral_to_stablecoin(position.collateral_amount, current_price, collateral_type.decimals);
+    if position_debt as f64 > position_value as f64 * collateral_type.loan_to_value {
+        return Err(StablecoinError::InsufficientCollateral.into());
+    }
+    position.store(&mut position_account.data.borrow_mut())?;
+
+    // Split off the mint fee unless the minter is exempt
+    let mint_fee = if treasury.fee_exempt_accounts.contains(minter.key) {
+        0
+    } else {
+        calculate_fee(tokens_to_mint, state.mint_fee_bps)?
+    };
+    let user_amount = tokens_to_mint - mint_fee;
+
+    if user_amount < min_out {
+        return Err(StablecoinError::SlippageExceeded.into());
+    }
+
+    // Move the deposit into the collateral type's vault
+    let deposit_ix = transfer(
         &spl_token::id(),
-        token_mint.key,
-        user_token_account.key,
-        mint_authority.key,
+        source_collateral_account.key,
+        collateral_vault.key,
+        minter.key,
         &[],
-        tokens_to_mint,
+        amount,
     )?;
 
-    solana_program::program::invoke_signed(
-        &mint_ix,
+    invoke(
+        &deposit_ix,
         &[
-            token_mint.clone(),
-            user_token_account.clone(),
-            mint_authority.clone(),
+            source_collateral_account.clone(),
+            collateral_vault.clone(),
+            minter.clone(),
         ],
-        &[],
     )?;
 
-    // Update config state
-    config.total_supply = config.total_supply.checked_add(tokens_to_mint)
-        .ok_or(StablecoinError::InvalidAmount)?;
-    config.last_oracle_price = current_price;
-    config.last_update_timestamp = clock.unix_timestamp;
-    config.serialize(&mut *config_account.data.borrow_mut())?;
+    // Mint tokens to user account
+    mint_tokens(
+        program_id,
+        config_account,
+        token_mint,
+        user_token_account,
+        mint_authority,
+        token_program,
+        user_amount,
+    )?;
+
+    if mint_fee > 0 {
+        validate_fee_collector_account(fee_collector_token_account, &config, &treasury)?;
+
+        mint_tokens(
+            program_id,
+            config_account,
+            token_mint,
+            fee_collector_token_account,
+            mint_authority,
+            token_program,
+            mint_fee,
+        )?;
+    }
+
+    // Update supply and collateral state
+    state.total_supply = new_total_supply;
+    state.last_update_timestamp = clock.unix_timestamp;
 
-    msg!("Minted {} tokens", tokens_to_mint);
+    collateral_type.debt = new_collateral_debt;
+    collateral_type.last_oracle_price = current_price;
+    collateral_type.store(&mut collateral_type_account.data.borrow_mut())?;
+
+    PriceUpdated {
+        config: *config_account.key,
+        collateral_mint: collateral_type.collateral_mint,
+        price: current_price,
+    }
+    .emit()?;
+    Minted {
+        config: *config_account.key,
+        minter: *minter.key,
+        recipient: user_token.owner,
+        collateral_mint: collateral_type.collateral_mint,
+        collateral_amount: amount,
+        amount: tokens_to_mint,
+        fee: mint_fee,
+    }
+    .emit()?;
+
+    msg!("Minted {} tokens ({} fee)", tokens_to_mint, mint_fee);
     Ok(())
 }
 
@@ -226,20 +1214,39 @@ fn process_redeem(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     amount: u64,
+    min_out: u64,
 ) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
     let config_account = next_account_info(accounts_iter)?;
+    let treasury_account = next_account_info(accounts_iter)?;
     let token_mint = next_account_info(accounts_iter)?;
     let user_token_account = next_account_info(accounts_iter)?;
     let oracle_account = next_account_info(accounts_iter)?;
     let user = next_account_info(accounts_iter)?;
     let clock_sysvar = next_account_info(accounts_iter)?;
+    let collateral_vault = next_account_info(accounts_iter)?;
+    let vault_authority = next_account_info(accounts_iter)?;
+    let destination_collateral_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let blocklist_account = next_account_info(accounts_iter)?;
+    let collateral_type_account = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+    let user_allowlist_entry = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
 
-    let mut config = StablecoinConfig::try_from_slice(&config_account.data.borrow())?;
+    let config = StablecoinConfig::load(&config_account.data.borrow())?;
+    let treasury: Treasury = load_config_account(program_id, config_account, treasury_account)?;
+    let mut state = load_state_mut(program_id, config_account, state_account)?;
 
-    // Check program is not paused
-    if config.is_paused {
-        return Err(StablecoinError::ProgramPaused.into());
+    // Check redemptions are not paused; after shutdown holders exit through
+    // ShutdownRedeem instead
+    state.ensure_not_paused(PAUSE_REDEEM)?;
+    if state.is_shut_down != 0 {
+        return Err(StablecoinError::ShutDown.into());
     }
 
     // Verify user is signer
@@ -247,13 +1254,35 @@ fn process_redeem(
         return Err(StablecoinError::Unauthorized.into());
     }
 
+    // Verify the burned tokens are this stablecoin and belong to the user
+    validate_token_program(token_program, &config)?;
+    validate_token_mint(token_mint, &config)?;
+    sync_total_supply(&mut state, token_mint)?;
+    let user_token = unpack_stablecoin_account(user_token_account, &config)?;
+    if user_token.owner != *user.key {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    ensure_not_blocked(program_id, config_account, blocklist_account, &[user.key])?;
+
+    // In KYC mode only allowlisted wallets may redeem
+    if config.kyc_mode && !is_allowlisted(program_id, config_account.key, user.key, user_allowlist_entry) {
+        return Err(StablecoinError::NotAllowlisted.into());
+    }
+
+    // Redemptions stay open on disabled collateral types so holders can exit
+    let mut collateral_type = load_collateral_type(program_id, config_account, collateral_type_account)?;
+    if *oracle_account.key != collateral_type.oracle {
+        return Err(StablecoinError::InvalidOraclePriceFeed.into());
+    }
+
     // Get and validate price
     let current_price = get_oracle_price(oracle_account)?;
     let oracle_data = OraclePrice::try_from_slice(&oracle_account.data.borrow())?;
     
     validate_price(
         current_price,
-        config.last_oracle_price,
+        collateral_type.last_oracle_price,
         oracle_data.confidence,
     )?;
 
@@ -263,12 +1292,95 @@ fn process_redeem(
         return Err(StablecoinError::StaleOracleData.into());
     }
 
+    accrue_stability_fees(&mut collateral_type, &mut state, clock.unix_timestamp)?;
+
+    // Redeemed tokens retire debt of the chosen position, which gives up the
+    // collateral paid out, so the vault keeps backing every position in full
+    if position_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mut position = Position::load(&position_account.data.borrow())?;
+    if position.config != *config_account.key || position.collateral_mint != collateral_type.collateral_mint {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let position_debt = denormalize_debt(position.normalized_debt, collateral_type.cumulative_rate_index);
+    if amount == 0 || amount > position_debt {
+        return Err(StablecoinError::InvalidAmount.into());
+    }
+
+    // Other holders may only redeem against positions past the threshold
+    if position.owner != *user.key
+        && !past_liquidation_threshold(
+            position_debt,
+            position.collateral_amount,
+            current_price,
+            &collateral_type,
+        )
+    {
+        return Err(StablecoinError::AboveLiquidationThreshold.into());
+    }
+
     // Calculate redemption amount
-    let redemption_amount = (amount as f64 * current_price) as u64;
+    let released_collateral = redemption_collateral(
+        amount,
+        current_price,
+        collateral_type.decimals,
+        &state,
+        position.collateral_amount,
+        position_debt,
+    )?;
+
+    // Retain the redemption fee as collateral owed to the fee collector
+    let redemption_fee = if treasury.fee_exempt_accounts.contains(user.key) {
+        0
+    } else {
+        calculate_fee(released_collateral, state.redeem_fee_bps)?
+    };
+    let redemption_amount = released_collateral - redemption_fee;
+
+    if redemption_amount < min_out {
+        return Err(StablecoinError::SlippageExceeded.into());
+    }
+
+    // Verify the vault is held by our PDA and can cover the payout on top of
+    // the fees already retained in it
+    let (vault_authority_pda, vault_bump) = Pubkey::find_program_address(
+        &[VAULT_AUTHORITY_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+    if vault_authority_pda != *vault_authority.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    if *collateral_vault.owner != spl_token::id() || *destination_collateral_account.owner != spl_token::id() {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    if *collateral_vault.key != collateral_type.vault {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    let vault = spl_token::state::Account::unpack(&collateral_vault.data.borrow())?;
+    if vault.owner != vault_authority_pda || vault.mint != collateral_type.collateral_mint {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    let destination = spl_token::state::Account::unpack(&destination_collateral_account.data.borrow())?;
+    if destination.mint != collateral_type.collateral_mint {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    let required_collateral = redemption_amount
+        .checked_add(collateral_type.accrued_redemption_fees)
+        .and_then(|total| total.checked_add(redemption_fee))
+        .ok_or(StablecoinError::InvalidAmount)?;
+    if vault.amount < required_collateral {
+        return Err(StablecoinError::InsufficientCollateral.into());
+    }
 
     // Burn tokens
     let burn_ix = burn(
-        &spl_token::id(),
+        token_program.key,
         user_token_account.key,
         token_mint.key,
         user.key,
@@ -276,7 +1388,7 @@ fn process_redeem(
         amount,
     )?;
 
-    solana_program::program::invoke(
+    invoke(
         &burn_ix,
         &[
             user_token_account.clone(),
@@ -285,13 +1397,3445 @@ fn process_redeem(
         ],
     )?;
 
-    // Update config state
-    config.total_supply = config.total_supply.checked_sub(amount)
+    // Pay out collateral from the vault
+    let transfer_ix = transfer(
+        &spl_token::id(),
+        collateral_vault.key,
+        destination_collateral_account.key,
+        vault_authority.key,
+        &[],
+        redemption_amount,
+    )?;
+
+    invoke_signed(
+        &transfer_ix,
+        &[
+            collateral_vault.clone(),
+            destination_collateral_account.clone(),
+            vault_authority.clone(),
+        ],
+        &[&[VAULT_AUTHORITY_SEED, config_account.key.as_ref(), &[vault_bump]]],
+    )?;
+
+    // Update supply and collateral state
+    state.total_supply = state.total_supply.checked_sub(amount)
+        .ok_or(StablecoinError::InvalidAmount)?;
+    state.last_update_timestamp = clock.unix_timestamp;
+
+    let normalized_amount = normalize_debt(amount, collateral_type.cumulative_rate_index)
+        .min(position.normalized_debt);
+    position.normalized_debt -= normalized_amount;
+    position.collateral_amount -= released_collateral;
+    position.store(&mut position_account.data.borrow_mut())?;
+
+    collateral_type.total_normalized_debt = collateral_type.total_normalized_debt.saturating_sub(normalized_amount);
+    collateral_type.debt = collateral_type.debt.saturating_sub(amount);
+    collateral_type.accrued_redemption_fees = collateral_type.accrued_redemption_fees
+        .checked_add(redemption_fee)
+        .ok_or(StablecoinError::InvalidAmount)?;
+    collateral_type.last_oracle_price = current_price;
+    collateral_type.store(&mut collateral_type_account.data.borrow_mut())?;
+
+    PriceUpdated {
+        config: *config_account.key,
+        collateral_mint: collateral_type.collateral_mint,
+        price: current_price,
+    }
+    .emit()?;
+    Redeemed {
+        config: *config_account.key,
+        user: *user.key,
+        collateral_mint: collateral_type.collateral_mint,
+        amount,
+        collateral_amount: redemption_amount,
+        fee: redemption_fee,
+    }
+    .emit()?;
+
+    msg!(
+        "Redeemed {} tokens for {} units of collateral ({} fee)",
+        amount,
+        redemption_amount,
+        redemption_fee
+    );
+    Ok(())
+}
+
+fn load_or_create_position<'a>(
+    program_id: &Pubkey,
+    config_account: &AccountInfo<'a>,
+    collateral_type: &CollateralType,
+    position_account: &AccountInfo<'a>,
+    owner: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+) -> Result<Position, ProgramError> {
+    let (position_pda, bump) = Pubkey::find_program_address(
+        &[
+            POSITION_SEED,
+            config_account.key.as_ref(),
+            collateral_type.collateral_mint.as_ref(),
+            owner.key.as_ref(),
+        ],
+        program_id,
+    );
+    if position_pda != *position_account.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    if !position_account.data_is_empty() {
+        if position_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        return Position::load(&position_account.data.borrow());
+    }
+
+    create_program_account(
+        program_id,
+        owner,
+        position_account,
+        system_program,
+        Position::LEN,
+        &[
+            POSITION_SEED,
+            config_account.key.as_ref(),
+            collateral_type.collateral_mint.as_ref(),
+            owner.key.as_ref(),
+            &[bump],
+        ],
+    )?;
+
+    Ok(Position {
+        owner: *owner.key,
+        config: *config_account.key,
+        collateral_mint: collateral_type.collateral_mint,
+        collateral_amount: 0,
+        normalized_debt: 0,
+    })
+}
+
+/// Loads a metadata, roles or treasury account belonging to `config_account`.
+fn load_config_account<T: ConfigAccount>(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+    account: &AccountInfo,
+) -> Result<T, ProgramError> {
+    if account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let loaded = T::load(&account.data.borrow())?;
+    if loaded.config() != config_account.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    Ok(loaded)
+}
+
+/// Creates a program-owned PDA of `space` bytes, funded for rent by `payer`.
+/// `create_account` refuses an address that already holds lamports, and anyone
+/// can send lamports to a predictable PDA, so a pre-funded address is topped
+/// up, allocated and assigned instead.
+fn create_program_account<'a>(
+    program_id: &Pubkey,
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let rent_lamports = Rent::get()?.minimum_balance(space);
+    if account.lamports() == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                account.key,
+                rent_lamports,
+                space as u64,
+                program_id,
+            ),
+            &[payer.clone(), account.clone(), system_program.clone()],
+            &[signer_seeds],
+        );
+    }
+
+    let shortfall = rent_lamports.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, account.key, shortfall),
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+    invoke_signed(
+        &system_instruction::allocate(account.key, space as u64),
+        &[account.clone(), system_program.clone()],
+        &[signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(account.key, program_id),
+        &[account.clone(), system_program.clone()],
+        &[signer_seeds],
+    )
+}
+
+/// Closes a program-owned account, refunding its rent to `destination`.
+fn close_program_account(account: &AccountInfo, destination: &AccountInfo) -> ProgramResult {
+    let lamports = account.lamports();
+    **account.try_borrow_mut_lamports()? = 0;
+    **destination.try_borrow_mut_lamports()? = destination.lamports()
+        .checked_add(lamports)
+        .ok_or(StablecoinError::InvalidAmount)?;
+    account.realloc(0, false)?;
+    account.assign(&system_program::id());
+    Ok(())
+}
+
+/// Borrows the hot state belonging to `config_account` in place, read-only.
+fn load_state<'a>(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+    state_account: &'a AccountInfo,
+) -> Result<Ref<'a, StablecoinState>, ProgramError> {
+    if state_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut error = None;
+    let state = Ref::filter_map(state_account.data.borrow(), |data| {
+        StablecoinState::load(data).map_err(|e| error = Some(e)).ok()
+    })
+    .map_err(|_| error.unwrap_or_else(|| StablecoinError::InvalidAccountType.into()))?;
+    if state.config != *config_account.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    Ok(state)
+}
+
+/// Borrows the hot state belonging to `config_account` in place.
+fn load_state_mut<'a>(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+    state_account: &'a AccountInfo,
+) -> Result<RefMut<'a, StablecoinState>, ProgramError> {
+    if state_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut error = None;
+    let state = RefMut::filter_map(state_account.data.borrow_mut(), |data| {
+        StablecoinState::load_mut(data).map_err(|e| error = Some(e)).ok()
+    })
+    .map_err(|_| error.unwrap_or_else(|| StablecoinError::InvalidAccountType.into()))?;
+    if state.config != *config_account.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    Ok(state)
+}
+
+/// Loads a collateral type registered under `config_account`.
+fn load_collateral_type(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+    collateral_type_account: &AccountInfo,
+) -> Result<CollateralType, ProgramError> {
+    if collateral_type_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let collateral_type = CollateralType::load(&collateral_type_account.data.borrow())?;
+    let (collateral_type_pda, _) = Pubkey::find_program_address(
+        &[COLLATERAL_TYPE_SEED, config_account.key.as_ref(), collateral_type.collateral_mint.as_ref()],
+        program_id,
+    );
+    if collateral_type.config != *config_account.key || collateral_type_pda != *collateral_type_account.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    Ok(collateral_type)
+}
+
+fn mint_tokens<'a>(
+    program_id: &Pubkey,
+    config_account: &AccountInfo<'a>,
+    token_mint: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    mint_authority: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    amount: u64,
+) -> ProgramResult {
+    let (mint_authority_pda, bump) = Pubkey::find_program_address(
+        &[MINT_AUTHORITY_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+    if mint_authority_pda != *mint_authority.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let mint_ix = mint_to(
+        token_program.key,
+        token_mint.key,
+        destination.key,
+        mint_authority.key,
+        &[],
+        amount,
+    )?;
+
+    invoke_signed(
+        &mint_ix,
+        &[
+            token_mint.clone(),
+            destination.clone(),
+            mint_authority.clone(),
+        ],
+        &[&[MINT_AUTHORITY_SEED, config_account.key.as_ref(), &[bump]]],
+    )
+}
+
+fn process_repay(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let token_mint = next_account_info(accounts_iter)?;
+    let user_token_account = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+    let owner = next_account_info(accounts_iter)?;
+    let clock_sysvar = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let collateral_type_account = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id || position_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // Verify position owner is signer
+    if !owner.is_signer {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    let config = StablecoinConfig::load(&config_account.data.borrow())?;
+    let mut state = load_state_mut(program_id, config_account, state_account)?;
+    let mut position = Position::load(&position_account.data.borrow())?;
+
+    // Debt is settled against the position's collateral instead
+    if state.is_shut_down != 0 {
+        return Err(StablecoinError::ShutDown.into());
+    }
+
+    if position.owner != *owner.key || position.config != *config_account.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    let mut collateral_type = load_collateral_type(program_id, config_account, collateral_type_account)?;
+    if collateral_type.collateral_mint != position.collateral_mint {
+        return Err(StablecoinError::InvalidMint.into());
+    }
+
+    validate_token_program(token_program, &config)?;
+    validate_token_mint(token_mint, &config)?;
+    let owner_token = unpack_stablecoin_account(user_token_account, &config)?;
+    if owner_token.owner != *owner.key {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    let clock = Clock::from_account_info(clock_sysvar)?;
+    accrue_stability_fees(&mut collateral_type, &mut state, clock.unix_timestamp)?;
+
+    // Cannot repay more than the position currently owes
+    let outstanding_debt = denormalize_debt(position.normalized_debt, collateral_type.cumulative_rate_index);
+    if amount == 0 || amount > outstanding_debt {
+        return Err(StablecoinError::InvalidAmount.into());
+    }
+
+    let burn_ix = burn(
+        token_program.key,
+        user_token_account.key,
+        token_mint.key,
+        owner.key,
+        &[],
+        amount,
+    )?;
+
+    invoke(
+        &burn_ix,
+        &[
+            user_token_account.clone(),
+            token_mint.clone(),
+            owner.clone(),
+        ],
+    )?;
+
+    let normalized_amount = normalize_debt(amount, collateral_type.cumulative_rate_index)
+        .min(position.normalized_debt);
+    position.normalized_debt -= normalized_amount;
+    collateral_type.total_normalized_debt = collateral_type.total_normalized_debt.saturating_sub(normalized_amount);
+    collateral_type.debt = collateral_type.debt.saturating_sub(amount);
+    state.total_supply = state.total_supply.checked_sub(amount)
         .ok_or(StablecoinError::InvalidAmount)?;
-    config.last_oracle_price = current_price;
-    config.last_update_timestamp = clock.unix_timestamp;
-    config.serialize(&mut *config_account.data.borrow_mut())?;
 
-    msg!("Redeemed {} tokens for {} units of fiat", amount, redemption_amount);
+    position.store(&mut position_account.data.borrow_mut())?;
+    collateral_type.store(&mut collateral_type_account.data.borrow_mut())?;
+
+    Repaid {
+        config: *config_account.key,
+        owner: *owner.key,
+        collateral_mint: collateral_type.collateral_mint,
+        amount,
+    }
+    .emit()?;
+
+    msg!("Repaid {} tokens of debt", amount);
     Ok(())
-} 
\ No newline at end of file
+}
+
+fn process_withdraw(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+    let owner = next_account_info(accounts_iter)?;
+    let oracle_account = next_account_info(accounts_iter)?;
+    let clock_sysvar = next_account_info(accounts_iter)?;
+    let collateral_type_account = next_account_info(accounts_iter)?;
+    let collateral_vault = next_account_info(accounts_iter)?;
+    let vault_authority = next_account_info(accounts_iter)?;
+    let destination_collateral_account = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id || position_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // Verify position owner is signer
+    if !owner.is_signer {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    let mut state = load_state_mut(program_id, config_account, state_account)?;
+
+    // After shutdown the collateral backs holders until positions settle
+    state.ensure_not_paused(PAUSE_WITHDRAW)?;
+    if state.is_shut_down != 0 {
+        return Err(StablecoinError::ShutDown.into());
+    }
+
+    let mut position = Position::load(&position_account.data.borrow())?;
+    if position.owner != *owner.key || position.config != *config_account.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    let mut collateral_type = load_collateral_type(program_id, config_account, collateral_type_account)?;
+    if collateral_type.collateral_mint != position.collateral_mint {
+        return Err(StablecoinError::InvalidMint.into());
+    }
+    if *oracle_account.key != collateral_type.oracle {
+        return Err(StablecoinError::InvalidOraclePriceFeed.into());
+    }
+    if *collateral_vault.key != collateral_type.vault {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    if amount == 0 || amount > position.collateral_amount {
+        return Err(StablecoinError::InvalidAmount.into());
+    }
+
+    let clock = Clock::from_account_info(clock_sysvar)?;
+    accrue_stability_fees(&mut collateral_type, &mut state, clock.unix_timestamp)?;
+
+    position.collateral_amount -= amount;
+
+    // Collateral still backing debt must stay within the loan-to-value at a
+    // fresh price; a repaid position withdraws freely
+    if position.normalized_debt > 0 {
+        let current_price = get_oracle_price(oracle_account)?;
+        let oracle_data = OraclePrice::try_from_slice(&oracle_account.data.borrow())?;
+
+        validate_price(
+            current_price,
+            collateral_type.last_oracle_price,
+            oracle_data.confidence,
+        )?;
+
+        if clock.unix_timestamp - oracle_data.last_update_timestamp > ORACLE_STALENESS_THRESHOLD {
+            return Err(StablecoinError::StaleOracleData.into());
+        }
+
+        let position_debt = denormalize_debt(position.normalized_debt, collateral_type.cumulative_rate_index);
+        let position_value = collateral_to_stablecoin(position.collateral_amount, current_price, collateral_type.decimals);
+        if position_debt as f64 > position_value as f64 * collateral_type.loan_to_value {
+            return Err(StablecoinError::InsufficientCollateral.into());
+        }
+        collateral_type.last_oracle_price = current_price;
+    }
+
+    let (vault_authority_pda, vault_bump) = Pubkey::find_program_address(
+        &[VAULT_AUTHORITY_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+    if vault_authority_pda != *vault_authority.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    if *destination_collateral_account.owner != spl_token::id() {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+    let destination = spl_token::state::Account::unpack(&destination_collateral_account.data.borrow())?;
+    if destination.mint != collateral_type.collateral_mint {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    let transfer_ix = transfer(
+        &spl_token::id(),
+        collateral_vault.key,
+        destination_collateral_account.key,
+        vault_authority.key,
+        &[],
+        amount,
+    )?;
+
+    invoke_signed(
+        &transfer_ix,
+        &[
+            collateral_vault.clone(),
+            destination_collateral_account.clone(),
+            vault_authority.clone(),
+        ],
+        &[&[VAULT_AUTHORITY_SEED, config_account.key.as_ref(), &[vault_bump]]],
+    )?;
+
+    collateral_type.store(&mut collateral_type_account.data.borrow_mut())?;
+
+    // An emptied position is closed and its rent returned to the owner
+    if position.collateral_amount == 0 && position.normalized_debt == 0 {
+        close_program_account(position_account, owner)?;
+    } else {
+        position.store(&mut position_account.data.borrow_mut())?;
+    }
+
+    Withdrawn {
+        config: *config_account.key,
+        owner: *owner.key,
+        collateral_mint: collateral_type.collateral_mint,
+        collateral_amount: amount,
+    }
+    .emit()?;
+
+    msg!("Withdrew {} units of collateral", amount);
+    Ok(())
+}
+
+fn process_collect_fees(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let treasury_account = next_account_info(accounts_iter)?;
+    let token_mint = next_account_info(accounts_iter)?;
+    let fee_collector_token_account = next_account_info(accounts_iter)?;
+    let minter = next_account_info(accounts_iter)?;
+    let clock_sysvar = next_account_info(accounts_iter)?;
+    let mint_authority = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let config = StablecoinConfig::load(&config_account.data.borrow())?;
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+    let treasury: Treasury = load_config_account(program_id, config_account, treasury_account)?;
+    let mut state = load_state_mut(program_id, config_account, state_account)?;
+
+    // Verify minter
+    if !minter.is_signer || !roles.authorized_minters.contains(minter.key) {
+        return Err(StablecoinError::UnauthorizedMinter.into());
+    }
+
+    // Fees would be minted without backing once the vaults are being paid out
+    if state.is_shut_down != 0 {
+        return Err(StablecoinError::ShutDown.into());
+    }
+
+    validate_token_program(token_program, &config)?;
+    validate_token_mint(token_mint, &config)?;
+    validate_fee_collector_account(fee_collector_token_account, &config, &treasury)?;
+
+    // Any collateral types passed after the fixed accounts are accrued first
+    let clock = Clock::from_account_info(clock_sysvar)?;
+    for collateral_type_account in accounts_iter {
+        let mut collateral_type = load_collateral_type(program_id, config_account, collateral_type_account)?;
+        accrue_stability_fees(&mut collateral_type, &mut state, clock.unix_timestamp)?;
+        collateral_type.store(&mut collateral_type_account.data.borrow_mut())?;
+    }
+
+    let fees = state.accrued_fees;
+    if fees == 0 {
+        return Err(StablecoinError::InsufficientStabilityFees.into());
+    }
+
+    mint_tokens(
+        program_id,
+        config_account,
+        token_mint,
+        fee_collector_token_account,
+        mint_authority,
+        token_program,
+        fees,
+    )?;
+
+    state.accrued_fees = 0;
+    state.total_supply = state.total_supply.checked_add(fees)
+        .ok_or(StablecoinError::InvalidAmount)?;
+
+    FeeCollected {
+        config: *config_account.key,
+        fee_collector: treasury.fee_collector,
+        amount: fees,
+        collateral_mint: None,
+    }
+    .emit()?;
+
+    msg!("Collected {} tokens in stability fees", fees);
+    Ok(())
+}
+
+fn validate_token_program(token_program: &AccountInfo, config: &StablecoinConfig) -> ProgramResult {
+    if *token_program.key != config.token_program {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+fn validate_token_mint(token_mint: &AccountInfo, config: &StablecoinConfig) -> ProgramResult {
+    if *token_mint.key != config.token_mint || *token_mint.owner != config.token_program {
+        return Err(StablecoinError::InvalidMint.into());
+    }
+    Ok(())
+}
+
+fn unpack_stablecoin_account(
+    token_account: &AccountInfo,
+    config: &StablecoinConfig,
+) -> Result<spl_token_2022::state::Account, ProgramError> {
+    if *token_account.owner != config.token_program {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    let data = token_account.data.borrow();
+    let account = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)
+        .map_err(|_| StablecoinError::InvalidTokenAccount)?
+        .base;
+    if account.mint != config.token_mint {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    Ok(account)
+}
+
+fn validate_fee_collector_account(
+    fee_collector_token_account: &AccountInfo,
+    config: &StablecoinConfig,
+    treasury: &Treasury,
+) -> ProgramResult {
+    // Fees may only be paid out to the configured collector
+    let fee_collector_token = unpack_stablecoin_account(fee_collector_token_account, config)?;
+    if fee_collector_token.owner != treasury.fee_collector {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+    Ok(())
+}
+
+fn read_mint_supply(token_mint: &AccountInfo) -> Result<u64, ProgramError> {
+    let data = token_mint.data.borrow();
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)
+        .map_err(|_| StablecoinError::InvalidMint)?;
+    Ok(mint.base.supply)
+}
+
+/// Checks `total_supply` against the SPL mint. Holders burning directly can only
+/// shrink the mint supply, which is absorbed here; any excess on the mint means
+/// tokens were issued outside the program and is rejected.
+fn sync_total_supply(state: &mut StablecoinState, token_mint: &AccountInfo) -> ProgramResult {
+    let mint_supply = read_mint_supply(token_mint)?;
+    if mint_supply == state.total_supply {
+        return Ok(());
+    }
+
+    msg!(
+        "Supply drift: recorded {} vs mint {} ({})",
+        state.total_supply,
+        mint_supply,
+        mint_supply as i128 - state.total_supply as i128
+    );
+    if mint_supply > state.total_supply {
+        return Err(StablecoinError::SupplyMismatch.into());
+    }
+
+    state.total_supply = mint_supply;
+    Ok(())
+}
+
+fn process_update_fees(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    mint_fee_bps: u16,
+    redeem_fee_bps: u16,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+    let mut state = load_state_mut(program_id, config_account, state_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    if mint_fee_bps > MAX_FEE_BPS || redeem_fee_bps > MAX_FEE_BPS {
+        return Err(StablecoinError::InvalidParameterAdjustment.into());
+    }
+
+    state.mint_fee_bps = mint_fee_bps;
+    state.redeem_fee_bps = redeem_fee_bps;
+
+    msg!("Fees updated: mint {} bps, redeem {} bps", mint_fee_bps, redeem_fee_bps);
+    Ok(())
+}
+
+fn process_add_fee_exemption(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    account: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let treasury_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+    let mut treasury: Treasury = load_config_account(program_id, config_account, treasury_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    if treasury.fee_exempt_accounts.contains(&account) {
+        return Err(StablecoinError::AuthorityAlreadyExists.into());
+    }
+    if treasury.fee_exempt_accounts.len() >= MAX_FEE_EXEMPT_ACCOUNTS {
+        return Err(StablecoinError::FeeExemptListFull.into());
+    }
+
+    treasury.fee_exempt_accounts.push(account);
+    treasury.store(&mut treasury_account.data.borrow_mut())?;
+
+    msg!("Added fee exemption for {}", account);
+    Ok(())
+}
+
+fn process_remove_fee_exemption(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    account: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let treasury_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+    let mut treasury: Treasury = load_config_account(program_id, config_account, treasury_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    let original_len = treasury.fee_exempt_accounts.len();
+    treasury.fee_exempt_accounts.retain(|exempt| *exempt != account);
+    if treasury.fee_exempt_accounts.len() == original_len {
+        return Err(StablecoinError::InvalidParameterAdjustment.into());
+    }
+
+    treasury.store(&mut treasury_account.data.borrow_mut())?;
+
+    msg!("Removed fee exemption for {}", account);
+    Ok(())
+}
+
+fn process_update_redemption_parameters(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    redemption_price: f64,
+    min_redemption_rate: f64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+    let mut state = load_state_mut(program_id, config_account, state_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    let valid_price = redemption_price.is_finite() && redemption_price > 0.0;
+    let valid_rate = min_redemption_rate > 0.0 && min_redemption_rate <= 1.0;
+    if !valid_price || !valid_rate {
+        return Err(StablecoinError::InvalidParameterAdjustment.into());
+    }
+
+    state.redemption_price = redemption_price;
+    state.min_redemption_rate = min_redemption_rate;
+
+    msg!(
+        "Redemption parameters updated: price {}, min rate {}",
+        redemption_price,
+        min_redemption_rate
+    );
+    Ok(())
+}
+
+fn process_update_metadata(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    name: String,
+    symbol: String,
+    icon_uri: String,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let coin_metadata_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+    let token_mint = next_account_info(accounts_iter)?;
+    let mint_authority = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    // Only required for classic SPL mints, whose metadata lives in Metaplex
+    let metaplex_accounts = (accounts_iter.next(), accounts_iter.next());
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let config = StablecoinConfig::load(&config_account.data.borrow())?;
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+    let mut coin_metadata: StablecoinMetadata = load_config_account(program_id, config_account, coin_metadata_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    validate_metadata_lengths(&name, &symbol, &icon_uri)?;
+    validate_token_program(token_program, &config)?;
+    validate_token_mint(token_mint, &config)?;
+
+    let (mint_authority_pda, bump) = Pubkey::find_program_address(
+        &[MINT_AUTHORITY_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+    if mint_authority_pda != *mint_authority.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Keep the on-chain token metadata in sync with the config
+    if config.token_program == spl_token_2022::id() {
+        update_token_metadata(
+            token_program,
+            token_mint,
+            mint_authority,
+            admin,
+            system_program,
+            &[MINT_AUTHORITY_SEED, config_account.key.as_ref(), &[bump]],
+            name.clone(),
+            symbol.clone(),
+            icon_uri.clone(),
+        )?;
+    } else {
+        let (Some(metadata_account), Some(token_metadata_program)) = metaplex_accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        validate_metaplex_accounts(metadata_account, token_metadata_program, token_mint.key)?;
+
+        // Until CreateMetaplexMetadata runs there is nothing to drift from; it
+        // is created from the stored copy
+        if !metadata_account.data_is_empty() {
+            update_metaplex_metadata(
+                token_metadata_program,
+                metadata_account,
+                mint_authority,
+                &[MINT_AUTHORITY_SEED, config_account.key.as_ref(), &[bump]],
+                name.clone(),
+                symbol.clone(),
+                icon_uri.clone(),
+            )?;
+        }
+    }
+
+    coin_metadata.name = name;
+    coin_metadata.symbol = symbol;
+    coin_metadata.icon_uri = icon_uri;
+
+    coin_metadata.store(&mut coin_metadata_account.data.borrow_mut())?;
+
+    msg!("Metadata updated: {} ({})", coin_metadata.name, coin_metadata.symbol);
+    Ok(())
+}
+
+fn process_create_metaplex_metadata(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let coin_metadata_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+    let token_mint = next_account_info(accounts_iter)?;
+    let mint_authority = next_account_info(accounts_iter)?;
+    let metadata_account = next_account_info(accounts_iter)?;
+    let token_metadata_program = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let config = StablecoinConfig::load(&config_account.data.borrow())?;
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+    let coin_metadata: StablecoinMetadata = load_config_account(program_id, config_account, coin_metadata_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    // Token-2022 mints carry their own metadata extension instead
+    if config.token_program != spl_token::id() {
+        return Err(StablecoinError::InvalidMint.into());
+    }
+    validate_token_mint(token_mint, &config)?;
+    validate_metaplex_accounts(metadata_account, token_metadata_program, token_mint.key)?;
+
+    let (mint_authority_pda, bump) = Pubkey::find_program_address(
+        &[MINT_AUTHORITY_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+    if mint_authority_pda != *mint_authority.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    create_metaplex_metadata(
+        token_metadata_program,
+        metadata_account,
+        token_mint,
+        mint_authority,
+        admin,
+        system_program,
+        &[MINT_AUTHORITY_SEED, config_account.key.as_ref(), &[bump]],
+        coin_metadata.name.clone(),
+        coin_metadata.symbol.clone(),
+        coin_metadata.icon_uri.clone(),
+    )?;
+
+    msg!("Created Metaplex metadata for {}", token_mint.key);
+    Ok(())
+}
+
+fn process_update_authority(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    authority_type: AuthorityType,
+    new_authority: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let treasury_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+    let mut treasury: Treasury = load_config_account(program_id, config_account, treasury_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    let authority = match authority_type {
+        AuthorityType::Admin => &mut roles.admin_authority,
+        AuthorityType::Oracle => &mut roles.oracle_authority,
+        AuthorityType::FeeCollector => &mut treasury.fee_collector,
+        AuthorityType::Compliance => &mut roles.compliance_authority,
+        AuthorityType::Treasury => &mut treasury.wallet,
+    };
+    if *authority == new_authority {
+        return Err(StablecoinError::AuthorityAlreadyExists.into());
+    }
+    let old_authority = std::mem::replace(authority, new_authority);
+
+    roles.store(&mut roles_account.data.borrow_mut())?;
+    treasury.store(&mut treasury_account.data.borrow_mut())?;
+
+    AuthorityChanged {
+        config: *config_account.key,
+        authority_type,
+        old_authority,
+        new_authority,
+    }
+    .emit()?;
+
+    msg!("Updated {:?} authority to {}", authority_type, new_authority);
+    Ok(())
+}
+
+fn process_freeze(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    freeze: bool,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let compliance_authority = next_account_info(accounts_iter)?;
+    let token_account = next_account_info(accounts_iter)?;
+    let token_mint = next_account_info(accounts_iter)?;
+    let freeze_authority = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let config = StablecoinConfig::load(&config_account.data.borrow())?;
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+
+    // Verify compliance authority
+    if !compliance_authority.is_signer || roles.compliance_authority != *compliance_authority.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    validate_token_program(token_program, &config)?;
+    validate_token_mint(token_mint, &config)?;
+    unpack_stablecoin_account(token_account, &config)?;
+
+    let (freeze_authority_pda, bump) = Pubkey::find_program_address(
+        &[FREEZE_AUTHORITY_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+    if freeze_authority_pda != *freeze_authority.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let ix = if freeze {
+        freeze_account(token_program.key, token_account.key, token_mint.key, freeze_authority.key, &[])?
+    } else {
+        thaw_account(token_program.key, token_account.key, token_mint.key, freeze_authority.key, &[])?
+    };
+
+    invoke_signed(
+        &ix,
+        &[
+            token_account.clone(),
+            token_mint.clone(),
+            freeze_authority.clone(),
+        ],
+        &[&[FREEZE_AUTHORITY_SEED, config_account.key.as_ref(), &[bump]]],
+    )?;
+
+    if freeze {
+        msg!("Froze token account {}", token_account.key);
+    } else {
+        msg!("Thawed token account {}", token_account.key);
+    }
+    Ok(())
+}
+
+fn load_blocklist(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+    blocklist_account: &AccountInfo,
+) -> Result<Option<Blocklist>, ProgramError> {
+    let (blocklist_pda, _) = Pubkey::find_program_address(
+        &[BLOCKLIST_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+    if blocklist_pda != *blocklist_account.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Nothing has been blocked until the blocklist is first written
+    if blocklist_account.data_is_empty() {
+        return Ok(None);
+    }
+    if blocklist_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let blocklist = Blocklist::load(&blocklist_account.data.borrow())?;
+    Ok(Some(blocklist))
+}
+
+fn ensure_not_blocked(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+    blocklist_account: &AccountInfo,
+    wallets: &[&Pubkey],
+) -> ProgramResult {
+    if let Some(blocklist) = load_blocklist(program_id, config_account, blocklist_account)? {
+        if wallets.iter().any(|wallet| blocklist.blocked_wallets.contains(wallet)) {
+            return Err(StablecoinError::WalletBlocked.into());
+        }
+    }
+    Ok(())
+}
+
+fn process_block_wallet(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    wallet: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let compliance_authority = next_account_info(accounts_iter)?;
+    let blocklist_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+
+    // Verify compliance authority
+    if !compliance_authority.is_signer || roles.compliance_authority != *compliance_authority.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    let mut blocklist = match load_blocklist(program_id, config_account, blocklist_account)? {
+        Some(blocklist) => blocklist,
+        None => {
+            let (_, bump) = Pubkey::find_program_address(
+                &[BLOCKLIST_SEED, config_account.key.as_ref()],
+                program_id,
+            );
+            create_program_account(
+                program_id,
+                compliance_authority,
+                blocklist_account,
+                system_program,
+                Blocklist::LEN,
+                &[BLOCKLIST_SEED, config_account.key.as_ref(), &[bump]],
+            )?;
+
+            Blocklist {
+                config: *config_account.key,
+                blocked_wallets: vec![],
+            }
+        }
+    };
+
+    if blocklist.blocked_wallets.contains(&wallet) {
+        return Err(StablecoinError::AuthorityAlreadyExists.into());
+    }
+    if blocklist.blocked_wallets.len() >= MAX_BLOCKLIST_ENTRIES {
+        return Err(StablecoinError::BlocklistFull.into());
+    }
+
+    blocklist.blocked_wallets.push(wallet);
+    blocklist.store(&mut blocklist_account.data.borrow_mut())?;
+
+    msg!("Blocked wallet {}", wallet);
+    Ok(())
+}
+
+fn process_unblock_wallet(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    wallet: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let compliance_authority = next_account_info(accounts_iter)?;
+    let blocklist_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+
+    // Verify compliance authority
+    if !compliance_authority.is_signer || roles.compliance_authority != *compliance_authority.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    let mut blocklist = load_blocklist(program_id, config_account, blocklist_account)?
+        .ok_or(StablecoinError::InvalidParameterAdjustment)?;
+
+    let original_len = blocklist.blocked_wallets.len();
+    blocklist.blocked_wallets.retain(|blocked| *blocked != wallet);
+    if blocklist.blocked_wallets.len() == original_len {
+        return Err(StablecoinError::InvalidParameterAdjustment.into());
+    }
+
+    blocklist.store(&mut blocklist_account.data.borrow_mut())?;
+
+    msg!("Unblocked wallet {}", wallet);
+    Ok(())
+}
+
+fn process_seize(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let treasury_account = next_account_info(accounts_iter)?;
+    let compliance_authority = next_account_info(accounts_iter)?;
+    let target_token_account = next_account_info(accounts_iter)?;
+    let token_mint = next_account_info(accounts_iter)?;
+    let freeze_authority = next_account_info(accounts_iter)?;
+    let permanent_delegate = next_account_info(accounts_iter)?;
+    let mint_authority = next_account_info(accounts_iter)?;
+    let treasury_token_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let config = StablecoinConfig::load(&config_account.data.borrow())?;
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+    let treasury: Treasury = load_config_account(program_id, config_account, treasury_account)?;
+
+    // Verify compliance authority
+    if !compliance_authority.is_signer || roles.compliance_authority != *compliance_authority.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    // Classic SPL Token has no authority able to burn from someone else's account
+    let permanent_delegate_pda = config.permanent_delegate
+        .ok_or(StablecoinError::ClawbackUnsupported)?;
+    if permanent_delegate_pda != *permanent_delegate.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let (_, permanent_delegate_bump) = Pubkey::find_program_address(
+        &[PERMANENT_DELEGATE_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+
+    let (freeze_authority_pda, freeze_bump) = Pubkey::find_program_address(
+        &[FREEZE_AUTHORITY_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+    if freeze_authority_pda != *freeze_authority.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    validate_token_program(token_program, &config)?;
+    validate_token_mint(token_mint, &config)?;
+
+    // Seizure is only valid against accounts already frozen by compliance
+    let target = unpack_stablecoin_account(target_token_account, &config)?;
+    if !target.is_frozen() {
+        return Err(StablecoinError::AccountNotFrozen.into());
+    }
+    if amount == 0 || amount > target.amount {
+        return Err(StablecoinError::InvalidAmount.into());
+    }
+
+    let treasury_token = unpack_stablecoin_account(treasury_token_account, &config)?;
+    if treasury_token.owner != treasury.wallet {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    let freeze_seeds: &[&[u8]] = &[FREEZE_AUTHORITY_SEED, config_account.key.as_ref(), &[freeze_bump]];
+    let freeze_accounts = [
+        target_token_account.clone(),
+        token_mint.clone(),
+        freeze_authority.clone(),
+    ];
+
+    // Frozen accounts cannot be burned from, so thaw for the duration of the burn
+    invoke_signed(
+        &thaw_account(token_program.key, target_token_account.key, token_mint.key, freeze_authority.key, &[])?,
+        &freeze_accounts,
+        &[freeze_seeds],
+    )?;
+
+    invoke_signed(
+        &burn(
+            token_program.key,
+            target_token_account.key,
+            token_mint.key,
+            permanent_delegate.key,
+            &[],
+            amount,
+        )?,
+        &[
+            target_token_account.clone(),
+            token_mint.clone(),
+            permanent_delegate.clone(),
+        ],
+        &[&[PERMANENT_DELEGATE_SEED, config_account.key.as_ref(), &[permanent_delegate_bump]]],
+    )?;
+
+    invoke_signed(
+        &freeze_account(token_program.key, target_token_account.key, token_mint.key, freeze_authority.key, &[])?,
+        &freeze_accounts,
+        &[freeze_seeds],
+    )?;
+
+    // Re-issue the seized amount to the treasury; total supply is unchanged
+    mint_tokens(
+        program_id,
+        config_account,
+        token_mint,
+        treasury_token_account,
+        mint_authority,
+        token_program,
+        amount,
+    )?;
+
+    Seized {
+        config: *config_account.key,
+        compliance_authority: *compliance_authority.key,
+        source: *target_token_account.key,
+        owner: target.owner,
+        destination: *treasury_token_account.key,
+        amount,
+    }
+    .emit()?;
+
+    let clock = Clock::get()?;
+    msg!(
+        "Seized {} tokens from {} (owner {}) to treasury {} by {} at {}",
+        amount,
+        target_token_account.key,
+        target.owner,
+        treasury_token_account.key,
+        compliance_authority.key,
+        clock.unix_timestamp
+    );
+    Ok(())
+}
+
+fn process_initialize_transfer_hook(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+    let extra_account_metas_account = next_account_info(accounts_iter)?;
+    let token_mint = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let config = StablecoinConfig::load(&config_account.data.borrow())?;
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    // Transfer hooks only exist on Token-2022
+    if config.token_program != spl_token_2022::id() {
+        return Err(StablecoinError::InvalidMint.into());
+    }
+    validate_token_mint(token_mint, &config)?;
+
+    let (extra_account_metas_pda, bump) =
+        get_extra_account_metas_address_and_bump_seed(token_mint.key, program_id);
+    if extra_account_metas_pda != *extra_account_metas_account.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if !extra_account_metas_account.data_is_empty() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let metas = extra_account_metas(config_account.key)?;
+    create_program_account(
+        program_id,
+        admin,
+        extra_account_metas_account,
+        system_program,
+        ExtraAccountMetaList::size_of(metas.len())?,
+        &collect_extra_account_metas_signer_seeds(token_mint.key, &[bump]),
+    )?;
+
+    ExtraAccountMetaList::init::<ExecuteInstruction>(
+        &mut extra_account_metas_account.data.borrow_mut(),
+        &metas,
+    )?;
+
+    msg!("Initialized transfer hook accounts for {}", token_mint.key);
+    Ok(())
+}
+
+fn process_set_kyc_mode(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    enabled: bool,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+    let token_mint = next_account_info(accounts_iter)?;
+    let mint_authority = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let extra_account_metas_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut config = StablecoinConfig::load(&config_account.data.borrow())?;
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    if config.token_program != spl_token_2022::id() {
+        return Err(StablecoinError::InvalidMint.into());
+    }
+    validate_token_program(token_program, &config)?;
+    validate_token_mint(token_mint, &config)?;
+
+    // Token-2022 cannot resolve the hook's accounts until they are initialized
+    let (extra_account_metas_pda, _) =
+        get_extra_account_metas_address_and_bump_seed(token_mint.key, program_id);
+    if extra_account_metas_pda != *extra_account_metas_account.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if enabled && extra_account_metas_account.data_is_empty() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let (mint_authority_pda, bump) = Pubkey::find_program_address(
+        &[MINT_AUTHORITY_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+    if mint_authority_pda != *mint_authority.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Attach or detach this program as the mint's transfer hook
+    invoke_signed(
+        &transfer_hook_instruction::update(
+            token_program.key,
+            token_mint.key,
+            mint_authority.key,
+            &[],
+            enabled.then_some(*program_id),
+        )?,
+        &[token_mint.clone(), mint_authority.clone()],
+        &[&[MINT_AUTHORITY_SEED, config_account.key.as_ref(), &[bump]]],
+    )?;
+
+    config.kyc_mode = enabled;
+    config.store(&mut config_account.data.borrow_mut())?;
+
+    msg!("KYC mode set to {}", enabled);
+    Ok(())
+}
+
+fn process_add_to_allowlist(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    wallet: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let compliance_authority = next_account_info(accounts_iter)?;
+    let allowlist_entry = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+
+    // Verify compliance authority
+    if !compliance_authority.is_signer || roles.compliance_authority != *compliance_authority.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    let (entry_pda, bump) = find_allowlist_entry_address(program_id, config_account.key, &wallet);
+    if entry_pda != *allowlist_entry.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if !allowlist_entry.data_is_empty() {
+        return Err(StablecoinError::AuthorityAlreadyExists.into());
+    }
+
+    create_program_account(
+        program_id,
+        compliance_authority,
+        allowlist_entry,
+        system_program,
+        AllowlistEntry::LEN,
+        &[ALLOWLIST_SEED, config_account.key.as_ref(), wallet.as_ref(), &[bump]],
+    )?;
+
+    let entry = AllowlistEntry {
+        config: *config_account.key,
+        wallet,
+    };
+    entry.store(&mut allowlist_entry.data.borrow_mut())?;
+
+    msg!("Allowlisted wallet {}", wallet);
+    Ok(())
+}
+
+fn process_remove_from_allowlist(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    wallet: Pubkey,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let compliance_authority = next_account_info(accounts_iter)?;
+    let allowlist_entry = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+
+    // Verify compliance authority
+    if !compliance_authority.is_signer || roles.compliance_authority != *compliance_authority.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    if !is_allowlisted(program_id, config_account.key, &wallet, allowlist_entry) {
+        return Err(StablecoinError::InvalidParameterAdjustment.into());
+    }
+
+    // Close the entry and refund its rent to the compliance authority
+    close_program_account(allowlist_entry, compliance_authority)?;
+
+    msg!("Removed wallet {} from allowlist", wallet);
+    Ok(())
+}
+
+fn process_transfer_hook_execute(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let source_account = next_account_info(accounts_iter)?;
+    let token_mint = next_account_info(accounts_iter)?;
+    let destination_account = next_account_info(accounts_iter)?;
+    let _authority = next_account_info(accounts_iter)?;
+    let _extra_account_metas_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let source_entry = next_account_info(accounts_iter)?;
+    let destination_entry = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let config = StablecoinConfig::load(&config_account.data.borrow())?;
+    validate_token_mint(token_mint, &config)?;
+
+    // Hook account lists created before the state was added do not resolve it
+    // until UpdateTransferHookAccounts rewrites them
+    if let Some(state_account) = accounts_iter.next() {
+        load_state(program_id, config_account, state_account)?.ensure_not_paused(PAUSE_TRANSFER_HOOK)?;
+    }
+
+    if !config.kyc_mode {
+        return Ok(());
+    }
+
+    // Both sides of the transfer must be KYC'd wallets
+    let source = unpack_stablecoin_account(source_account, &config)?;
+    let destination = unpack_stablecoin_account(destination_account, &config)?;
+    if !is_allowlisted(program_id, config_account.key, &source.owner, source_entry)
+        || !is_allowlisted(program_id, config_account.key, &destination.owner, destination_entry)
+    {
+        msg!("Transfer of {} from {} to {} rejected", amount, source.owner, destination.owner);
+        return Err(StablecoinError::TransferNotAllowed.into());
+    }
+
+    Ok(())
+}
+
+fn process_reconcile(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+    let token_mint = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let config = StablecoinConfig::load(&config_account.data.borrow())?;
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+    let mut state = load_state_mut(program_id, config_account, state_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    validate_token_mint(token_mint, &config)?;
+
+    // The mint is the source of truth in either direction
+    let mint_supply = read_mint_supply(token_mint)?;
+    let drift = mint_supply as i128 - state.total_supply as i128;
+    state.total_supply = mint_supply;
+
+    msg!("Reconciled total supply to {} (drift {})", mint_supply, drift);
+    Ok(())
+}
+
+fn process_update_debt_ceiling(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    debt_ceiling: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+    let mut state = load_state_mut(program_id, config_account, state_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    state.debt_ceiling = debt_ceiling;
+
+    msg!("Global debt ceiling updated to {}", debt_ceiling);
+    Ok(())
+}
+
+fn validate_collateral_parameters(params: &CollateralParameters, debt_ceiling: u64) -> ProgramResult {
+    // Positions must become liquidatable before they can be opened at max LTV
+    let valid_ratios = params.loan_to_value > 0.0
+        && params.loan_to_value <= params.liquidation_threshold
+        && params.liquidation_threshold <= 1.0;
+    let valid_fee = (0.0..=MAX_STABILITY_FEE_RATE).contains(&params.stability_fee_rate);
+    // A collateral type can never be allowed more than the whole system
+    if !valid_ratios || !valid_fee || params.debt_ceiling > debt_ceiling {
+        return Err(StablecoinError::InvalidParameterAdjustment.into());
+    }
+    Ok(())
+}
+
+fn process_add_collateral_type(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    params: CollateralParameters,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+    let collateral_type_account = next_account_info(accounts_iter)?;
+    let collateral_mint = next_account_info(accounts_iter)?;
+    let oracle_account = next_account_info(accounts_iter)?;
+    let collateral_vault = next_account_info(accounts_iter)?;
+    let clock_sysvar = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+    let mut state = load_state_mut(program_id, config_account, state_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    if state.is_shut_down != 0 {
+        return Err(StablecoinError::ShutDown.into());
+    }
+
+    validate_collateral_parameters(&params, state.debt_ceiling)?;
+
+    let (collateral_type_pda, bump) = Pubkey::find_program_address(
+        &[COLLATERAL_TYPE_SEED, config_account.key.as_ref(), collateral_mint.key.as_ref()],
+        program_id,
+    );
+    if collateral_type_pda != *collateral_type_account.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if !collateral_type_account.data_is_empty() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    if *collateral_mint.owner != spl_token::id() {
+        return Err(StablecoinError::InvalidMint.into());
+    }
+    let mint = spl_token::state::Mint::unpack(&collateral_mint.data.borrow())?;
+
+    // Collateral is only ever paid out of a vault held by our PDA
+    let (vault_authority_pda, _) = Pubkey::find_program_address(
+        &[VAULT_AUTHORITY_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+    if *collateral_vault.owner != spl_token::id() {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+    let vault = spl_token::state::Account::unpack(&collateral_vault.data.borrow())?;
+    if vault.owner != vault_authority_pda || vault.mint != *collateral_mint.key {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    get_oracle_price(oracle_account)?;
+
+    create_program_account(
+        program_id,
+        admin,
+        collateral_type_account,
+        system_program,
+        CollateralType::LEN,
+        &[COLLATERAL_TYPE_SEED, config_account.key.as_ref(), collateral_mint.key.as_ref(), &[bump]],
+    )?;
+
+    let collateral_type = CollateralType {
+        config: *config_account.key,
+        collateral_mint: *collateral_mint.key,
+        oracle: *oracle_account.key,
+        vault: *collateral_vault.key,
+        decimals: mint.decimals,
+        loan_to_value: params.loan_to_value,
+        liquidation_threshold: params.liquidation_threshold,
+        stability_fee_rate: params.stability_fee_rate,
+        cumulative_rate_index: 1.0,
+        last_fee_accrual_timestamp: Clock::from_account_info(clock_sysvar)?.unix_timestamp,
+        total_normalized_debt: 0,
+        debt_ceiling: params.debt_ceiling,
+        debt: 0,
+        accrued_redemption_fees: 0,
+        last_oracle_price: 0.0,
+        is_enabled: true,
+    };
+    collateral_type.store(&mut collateral_type_account.data.borrow_mut())?;
+
+    state.collateral_types = state.collateral_types.checked_add(1)
+        .ok_or(StablecoinError::InvalidAmount)?;
+
+    msg!("Collateral type {} added", collateral_mint.key);
+    Ok(())
+}
+
+fn process_update_collateral_type(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    params: CollateralParameters,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+    let collateral_type_account = next_account_info(accounts_iter)?;
+    let oracle_account = next_account_info(accounts_iter)?;
+    let clock_sysvar = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+    let mut state = load_state_mut(program_id, config_account, state_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    validate_collateral_parameters(&params, state.debt_ceiling)?;
+
+    let mut collateral_type = load_collateral_type(program_id, config_account, collateral_type_account)?;
+
+    // Settle fees at the old rate before switching
+    let clock = Clock::from_account_info(clock_sysvar)?;
+    accrue_stability_fees(&mut collateral_type, &mut state, clock.unix_timestamp)?;
+
+    // A new feed starts without a reference price for the deviation check
+    if *oracle_account.key != collateral_type.oracle {
+        get_oracle_price(oracle_account)?;
+        collateral_type.oracle = *oracle_account.key;
+        collateral_type.last_oracle_price = 0.0;
+    }
+
+    collateral_type.loan_to_value = params.loan_to_value;
+    collateral_type.liquidation_threshold = params.liquidation_threshold;
+    collateral_type.stability_fee_rate = params.stability_fee_rate;
+    collateral_type.debt_ceiling = params.debt_ceiling;
+    collateral_type.store(&mut collateral_type_account.data.borrow_mut())?;
+
+    msg!("Collateral type {} updated", collateral_type.collateral_mint);
+    Ok(())
+}
+
+fn process_disable_collateral_type(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+    let collateral_type_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    // Existing positions can still repay and holders can still redeem
+    let mut collateral_type = load_collateral_type(program_id, config_account, collateral_type_account)?;
+    collateral_type.is_enabled = false;
+    collateral_type.store(&mut collateral_type_account.data.borrow_mut())?;
+
+    msg!("Collateral type {} disabled", collateral_type.collateral_mint);
+    Ok(())
+}
+
+/// Config layout before the live accounting moved into `StablecoinState`.
+#[derive(BorshDeserialize)]
+struct StablecoinConfigV1 {
+    is_initialized: bool,
+    name: String,
+    symbol: String,
+    icon_uri: String,
+    target_currency: String,
+    mint_authority: Pubkey,
+    oracle_pubkey: Pubkey,
+    total_supply: u64,
+    collateral_ratio: u64,
+    last_update_timestamp: i64,
+    admin_authority: Pubkey,
+    oracle_authority: Pubkey,
+    fee_collector: Pubkey,
+    is_paused: bool,
+    authorized_minters: Vec<Pubkey>,
+    accrued_fees: u64,
+    mint_fee_bps: u16,
+    redeem_fee_bps: u16,
+    fee_exempt_accounts: Vec<Pubkey>,
+    redemption_price: f64,
+    min_redemption_rate: f64,
+    token_mint: Pubkey,
+    freeze_authority: Pubkey,
+    token_program: Pubkey,
+    compliance_authority: Pubkey,
+    permanent_delegate: Option<Pubkey>,
+    treasury: Pubkey,
+    kyc_mode: bool,
+    debt_ceiling: u64,
+}
+
+impl StablecoinConfigV1 {
+    /// Moves the live accounting into `state`, leaving the version 2 layout.
+    fn into_v2(self, state: &mut StablecoinState) -> StablecoinConfigV2 {
+        state.pause_flags = if self.is_paused { PAUSE_ALL } else { 0 };
+        state.total_supply = self.total_supply;
+        state.accrued_fees = self.accrued_fees;
+        state.debt_ceiling = self.debt_ceiling;
+        state.last_update_timestamp = self.last_update_timestamp;
+        state.redemption_price = self.redemption_price;
+        state.min_redemption_rate = self.min_redemption_rate;
+        state.mint_fee_bps = self.mint_fee_bps;
+        state.redeem_fee_bps = self.redeem_fee_bps;
+
+        StablecoinConfigV2 {
+            is_initialized: self.is_initialized,
+            name: self.name,
+            symbol: self.symbol,
+            icon_uri: self.icon_uri,
+            target_currency: self.target_currency,
+            mint_authority: self.mint_authority,
+            oracle_pubkey: self.oracle_pubkey,
+            collateral_ratio: self.collateral_ratio,
+            admin_authority: self.admin_authority,
+            oracle_authority: self.oracle_authority,
+            fee_collector: self.fee_collector,
+            authorized_minters: self.authorized_minters,
+            fee_exempt_accounts: self.fee_exempt_accounts,
+            token_mint: self.token_mint,
+            freeze_authority: self.freeze_authority,
+            token_program: self.token_program,
+            compliance_authority: self.compliance_authority,
+            permanent_delegate: self.permanent_delegate,
+            treasury: self.treasury,
+            kyc_mode: self.kyc_mode,
+        }
+    }
+}
+
+/// Config layout before metadata, roles and treasury settings were split out.
+#[derive(BorshDeserialize)]
+struct StablecoinConfigV2 {
+    is_initialized: bool,
+    name: String,
+    symbol: String,
+    icon_uri: String,
+    target_currency: String,
+    mint_authority: Pubkey,
+    oracle_pubkey: Pubkey,
+    collateral_ratio: u64,
+    admin_authority: Pubkey,
+    oracle_authority: Pubkey,
+    fee_collector: Pubkey,
+    authorized_minters: Vec<Pubkey>,
+    fee_exempt_accounts: Vec<Pubkey>,
+    token_mint: Pubkey,
+    freeze_authority: Pubkey,
+    token_program: Pubkey,
+    compliance_authority: Pubkey,
+    permanent_delegate: Option<Pubkey>,
+    treasury: Pubkey,
+    kyc_mode: bool,
+}
+
+enum LegacyConfig {
+    V1(StablecoinConfigV1),
+    V2(StablecoinConfigV2),
+}
+
+/// Reads a config written by an earlier layout, or `None` if it is current.
+fn load_legacy_config(data: &[u8]) -> Result<Option<LegacyConfig>, ProgramError> {
+    match account_version::<StablecoinConfig>(data) {
+        Some(StablecoinConfig::VERSION) => Ok(None),
+        Some(2) => Ok(Some(LegacyConfig::V2(StablecoinConfigV2::deserialize(&mut &data[HEADER_LEN..])?))),
+        Some(1) => Ok(Some(LegacyConfig::V1(StablecoinConfigV1::deserialize(&mut &data[HEADER_LEN..])?))),
+        // Headerless configs predate the token mint and the config PDA derived
+        // from it, so there is nothing to migrate them onto; those deployments
+        // are redeployed instead
+        _ => Err(StablecoinError::UnsupportedAccountVersion.into()),
+    }
+}
+
+fn process_migrate_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+    let coin_metadata_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let treasury_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let Some(legacy) = load_legacy_config(&config_account.data.borrow())? else {
+        msg!("Config is already at version {}", StablecoinConfig::VERSION);
+        return Ok(());
+    };
+
+    // Verify admin authority
+    let admin_authority = match &legacy {
+        LegacyConfig::V1(legacy) => legacy.admin_authority,
+        LegacyConfig::V2(legacy) => legacy.admin_authority,
+    };
+    if !admin.is_signer || admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    let (state_pda, state_bump) = find_state_address(program_id, config_account.key);
+    let (coin_metadata_pda, coin_metadata_bump) = find_metadata_address(program_id, config_account.key);
+    let (roles_pda, roles_bump) = find_roles_address(program_id, config_account.key);
+    let (treasury_pda, treasury_bump) = find_treasury_address(program_id, config_account.key);
+    if state_pda != *state_account.key
+        || coin_metadata_pda != *coin_metadata_account.key
+        || roles_pda != *roles_account.key
+        || treasury_pda != *treasury_account.key
+    {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let legacy = match legacy {
+        LegacyConfig::V2(legacy) => legacy,
+        // Version 1 still held the live accounting
+        LegacyConfig::V1(legacy) => {
+            create_program_account(
+                program_id,
+                admin,
+                state_account,
+                system_program,
+                StablecoinState::LEN,
+                &[STATE_SEED, config_account.key.as_ref(), &[state_bump]],
+            )?;
+            let mut state_data = state_account.data.borrow_mut();
+            let state = StablecoinState::init(&mut state_data)?;
+            state.config = *config_account.key;
+            legacy.into_v2(state)
+        }
+    };
+
+    // Every collateral type already registered follows the fixed accounts, as
+    // types added before the state counted them are missing from its count
+    let mut collateral_types: Vec<&Pubkey> = Vec::new();
+    for collateral_type_account in accounts_iter {
+        load_collateral_type(program_id, config_account, collateral_type_account)?;
+        if collateral_types.contains(&collateral_type_account.key) {
+            return Err(StablecoinError::IncompleteCollateralTypes.into());
+        }
+        collateral_types.push(collateral_type_account.key);
+    }
+    let registered = u8::try_from(collateral_types.len()).map_err(|_| StablecoinError::InvalidAmount)?;
+    {
+        let mut state = load_state_mut(program_id, config_account, state_account)?;
+        state.collateral_types = state.collateral_types.max(registered);
+    }
+
+    let coin_metadata = StablecoinMetadata {
+        config: *config_account.key,
+        name: legacy.name,
+        symbol: legacy.symbol,
+        icon_uri: legacy.icon_uri,
+        target_currency: legacy.target_currency,
+    };
+    // Bounds were not enforced before version 2
+    let coin_metadata_len = StablecoinMetadata::LEN.max(coin_metadata.to_account_data()?.len());
+    create_program_account(
+        program_id,
+        admin,
+        coin_metadata_account,
+        system_program,
+        coin_metadata_len,
+        &[METADATA_SEED, config_account.key.as_ref(), &[coin_metadata_bump]],
+    )?;
+    coin_metadata.store(&mut coin_metadata_account.data.borrow_mut())?;
+
+    let roles = Roles {
+        config: *config_account.key,
+        admin_authority: legacy.admin_authority,
+        oracle_authority: legacy.oracle_authority,
+        compliance_authority: legacy.compliance_authority,
+        authorized_minters: legacy.authorized_minters,
+    };
+    create_program_account(
+        program_id,
+        admin,
+        roles_account,
+        system_program,
+        Roles::LEN.max(roles.to_account_data()?.len()),
+        &[ROLES_SEED, config_account.key.as_ref(), &[roles_bump]],
+    )?;
+    roles.store(&mut roles_account.data.borrow_mut())?;
+
+    let treasury = Treasury {
+        config: *config_account.key,
+        fee_collector: legacy.fee_collector,
+        wallet: legacy.treasury,
+        fee_exempt_accounts: legacy.fee_exempt_accounts,
+    };
+    create_program_account(
+        program_id,
+        admin,
+        treasury_account,
+        system_program,
+        Treasury::LEN,
+        &[TREASURY_SEED, config_account.key.as_ref(), &[treasury_bump]],
+    )?;
+    treasury.store(&mut treasury_account.data.borrow_mut())?;
+
+    let config = StablecoinConfig {
+        is_initialized: legacy.is_initialized,
+        mint_authority: legacy.mint_authority,
+        oracle_pubkey: legacy.oracle_pubkey,
+        collateral_ratio: legacy.collateral_ratio,
+        token_mint: legacy.token_mint,
+        freeze_authority: legacy.freeze_authority,
+        token_program: legacy.token_program,
+        permanent_delegate: legacy.permanent_delegate,
+        kyc_mode: legacy.kyc_mode,
+    };
+    if StablecoinConfig::LEN > config_account.data_len() {
+        let shortfall = Rent::get()?
+            .minimum_balance(StablecoinConfig::LEN)
+            .saturating_sub(config_account.lamports());
+        if shortfall > 0 {
+            invoke(
+                &system_instruction::transfer(admin.key, config_account.key, shortfall),
+                &[admin.clone(), config_account.clone(), system_program.clone()],
+            )?;
+        }
+        config_account.realloc(StablecoinConfig::LEN, true)?;
+    }
+    let mut data = config_account.data.borrow_mut();
+    data.fill(0);
+    config.store(&mut data)?;
+
+    msg!("Config migrated to version {}", StablecoinConfig::VERSION);
+    Ok(())
+}
+
+/// Winds the deployment down for good: minting stops and each collateral
+/// type's price is frozen at its oracle's current reading. Every registered
+/// collateral type must be passed; each gets a settlement splitting its vault
+/// between the holders, who redeem with `ShutdownRedeem`, and the position
+/// owners, who reclaim their excess with `SettlePosition`.
+fn process_shutdown(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+    let token_mint = next_account_info(accounts_iter)?;
+    let clock_sysvar = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let config = StablecoinConfig::load(&config_account.data.borrow())?;
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+    let mut state = load_state_mut(program_id, config_account, state_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    if state.is_shut_down != 0 {
+        return Err(StablecoinError::ShutDown.into());
+    }
+
+    validate_token_mint(token_mint, &config)?;
+    sync_total_supply(&mut state, token_mint)?;
+
+    // Collateral types follow as (collateral type, oracle, vault, settlement)
+    let collateral_accounts = accounts_iter.as_slice();
+    if !collateral_accounts.len().is_multiple_of(4) {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let clock = Clock::from_account_info(clock_sysvar)?;
+    let mut frozen = Vec::new();
+    for quad in collateral_accounts.chunks_exact(4) {
+        let (collateral_type_account, oracle_account) = (&quad[0], &quad[1]);
+        let mut collateral_type = load_collateral_type(program_id, config_account, collateral_type_account)?;
+        if *oracle_account.key != collateral_type.oracle {
+            return Err(StablecoinError::InvalidOraclePriceFeed.into());
+        }
+
+        let current_price = get_oracle_price(oracle_account)?;
+        let oracle_data = OraclePrice::try_from_slice(&oracle_account.data.borrow())?;
+        if clock.unix_timestamp - oracle_data.last_update_timestamp > ORACLE_STALENESS_THRESHOLD {
+            return Err(StablecoinError::StaleOracleData.into());
+        }
+        collateral_type.last_oracle_price = current_price;
+
+        PriceUpdated {
+            config: *config_account.key,
+            collateral_mint: collateral_type.collateral_mint,
+            price: current_price,
+        }
+        .emit()?;
+
+        frozen.push(freeze_collateral_type(
+            program_id,
+            config_account,
+            collateral_type_account,
+            collateral_type,
+            &quad[2],
+            &quad[3],
+            &mut state,
+            clock.unix_timestamp,
+        )?);
+    }
+
+    create_settlements(program_id, config_account, admin, system_program, &state, &frozen, clock.unix_timestamp)?;
+
+    state.is_shut_down = 1;
+    state.shutdown_collateral_types = state.collateral_types;
+    state.last_update_timestamp = clock.unix_timestamp;
+
+    ShutDown {
+        config: *config_account.key,
+        emergency: false,
+        collateral_types: state.shutdown_collateral_types,
+        total_supply: state.total_supply,
+    }
+    .emit()?;
+
+    msg!("Stablecoin shut down with {} collateral types", frozen.len());
+    Ok(())
+}
+
+/// The share of `available` collateral paid for `amount` of the remaining
+/// `total_supply`. The last redemption takes whatever rounding left behind, so
+/// the holder pool ends up empty.
+fn shutdown_payout(available: u64, amount: u64, total_supply: u64) -> u64 {
+    if amount == total_supply {
+        available
+    } else {
+        (available as u128 * amount as u128 / total_supply as u128) as u64
+    }
+}
+
+/// Closes the deployment once shutdown redemptions have retired the whole
+/// supply and the position owners have settled, or `SETTLEMENT_CLAIM_PERIOD`
+/// has passed since the shutdown. Retained redemption fees, and any owner
+/// collateral left unclaimed after that period, are swept to the fee
+/// collector; the collateral vaults, settlements and blocklist are closed, and
+/// all rent is refunded to the admin. Allowlist entries must be removed with
+/// `RemoveFromAllowlist` beforehand, as nothing can authorize closing them
+/// once the roles are gone.
+fn process_close_deployment(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let treasury_account = next_account_info(accounts_iter)?;
+    let coin_metadata_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+    let token_mint = next_account_info(accounts_iter)?;
+    let vault_authority = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+    let blocklist_account = next_account_info(accounts_iter)?;
+    let clock_sysvar = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let config = StablecoinConfig::load(&config_account.data.borrow())?;
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+    let treasury: Treasury = load_config_account(program_id, config_account, treasury_account)?;
+    let blocklist = load_blocklist(program_id, config_account, blocklist_account)?;
+    let _: StablecoinMetadata = load_config_account(program_id, config_account, coin_metadata_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    let shutdown_collateral_types = {
+        let mut state = load_state_mut(program_id, config_account, state_account)?;
+        if state.is_shut_down == 0 {
+            return Err(StablecoinError::NotShutDown.into());
+        }
+        // Settlement stays open indefinitely for late owners and holders
+        if state.is_emergency_shutdown != 0 {
+            return Err(StablecoinError::EmergencySettlement.into());
+        }
+
+        validate_token_mint(token_mint, &config)?;
+        sync_total_supply(&mut state, token_mint)?;
+        if state.total_supply != 0 {
+            return Err(StablecoinError::SupplyOutstanding.into());
+        }
+
+        state.shutdown_collateral_types
+    };
+
+    let (vault_authority_pda, vault_bump) = Pubkey::find_program_address(
+        &[VAULT_AUTHORITY_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+    if vault_authority_pda != *vault_authority.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let vault_authority_seeds: &[&[u8]] = &[VAULT_AUTHORITY_SEED, config_account.key.as_ref(), &[vault_bump]];
+
+    // Every frozen collateral type follows as (collateral type, vault,
+    // settlement, fee collector's collateral account) so nothing is left
+    // orphaned
+    let collateral_accounts = accounts_iter.as_slice();
+    if collateral_accounts.len() != 4 * shutdown_collateral_types as usize {
+        return Err(StablecoinError::IncompleteCollateralTypes.into());
+    }
+
+    let clock = Clock::from_account_info(clock_sysvar)?;
+    for quad in collateral_accounts.chunks_exact(4) {
+        let (collateral_type_account, collateral_vault, settlement_account, fee_collector_collateral_account) =
+            (&quad[0], &quad[1], &quad[2], &quad[3]);
+
+        // A closed collateral type no longer loads, so duplicates fail here
+        let collateral_type = load_collateral_type(program_id, config_account, collateral_type_account)?;
+        if *collateral_vault.key != collateral_type.vault || *collateral_vault.owner != spl_token::id() {
+            return Err(StablecoinError::InvalidTokenAccount.into());
+        }
+
+        let settlement: Settlement = load_config_account(program_id, config_account, settlement_account)?;
+        if settlement.collateral_mint != collateral_type.collateral_mint {
+            return Err(StablecoinError::InvalidMint.into());
+        }
+
+        // Owners who have not settled keep their claim until the period ends
+        if settlement.owner_collateral > 0
+            && clock.unix_timestamp - settlement.settled_at < SETTLEMENT_CLAIM_PERIOD
+        {
+            return Err(StablecoinError::SettlementOutstanding.into());
+        }
+
+        let vault = spl_token::state::Account::unpack(&collateral_vault.data.borrow())?;
+        if vault.owner != vault_authority_pda {
+            return Err(StablecoinError::InvalidTokenAccount.into());
+        }
+
+        // What remains is the retained fees, unclaimed owner collateral and
+        // anything sent to the vault from outside, none of which has another
+        // claimant
+        if vault.amount > 0 {
+            if *fee_collector_collateral_account.owner != spl_token::id() {
+                return Err(StablecoinError::InvalidTokenAccount.into());
+            }
+            let fee_collector_collateral = spl_token::state::Account::unpack(
+                &fee_collector_collateral_account.data.borrow(),
+            )?;
+            if fee_collector_collateral.owner != treasury.fee_collector
+                || fee_collector_collateral.mint != collateral_type.collateral_mint
+            {
+                return Err(StablecoinError::InvalidTokenAccount.into());
+            }
+
+            let transfer_ix = transfer(
+                &spl_token::id(),
+                collateral_vault.key,
+                fee_collector_collateral_account.key,
+                vault_authority.key,
+                &[],
+                vault.amount,
+            )?;
+
+            invoke_signed(
+                &transfer_ix,
+                &[
+                    collateral_vault.clone(),
+                    fee_collector_collateral_account.clone(),
+                    vault_authority.clone(),
+                ],
+                &[vault_authority_seeds],
+            )?;
+        }
+
+        let close_ix = close_account(
+            &spl_token::id(),
+            collateral_vault.key,
+            admin.key,
+            vault_authority.key,
+            &[],
+        )?;
+
+        invoke_signed(
+            &close_ix,
+            &[
+                collateral_vault.clone(),
+                admin.clone(),
+                vault_authority.clone(),
+            ],
+            &[vault_authority_seeds],
+        )?;
+
+        close_program_account(settlement_account, admin)?;
+        close_program_account(collateral_type_account, admin)?;
+    }
+
+    if blocklist.is_some() {
+        close_program_account(blocklist_account, admin)?;
+    }
+    close_program_account(state_account, admin)?;
+    close_program_account(coin_metadata_account, admin)?;
+    close_program_account(roles_account, admin)?;
+    close_program_account(treasury_account, admin)?;
+    close_program_account(config_account, admin)?;
+
+    msg!("Closed stablecoin deployment {}", config.token_mint);
+    Ok(())
+}
+
+/// Enters irreversible emergency settlement without trusting the oracle again:
+/// each collateral type is valued at the last price the program accepted, and
+/// its vault is split between the collateral backing the outstanding supply
+/// and the excess owed back to position owners. Every registered collateral
+/// type must be passed.
+fn process_emergency_shutdown(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+    let token_mint = next_account_info(accounts_iter)?;
+    let clock_sysvar = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let config = StablecoinConfig::load(&config_account.data.borrow())?;
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+    let mut state = load_state_mut(program_id, config_account, state_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    if state.is_shut_down != 0 {
+        return Err(StablecoinError::ShutDown.into());
+    }
+
+    validate_token_mint(token_mint, &config)?;
+    sync_total_supply(&mut state, token_mint)?;
+
+    // Collateral types follow as (collateral type, vault, settlement) triples
+    let collateral_accounts = accounts_iter.as_slice();
+    if !collateral_accounts.len().is_multiple_of(3) {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    // Freeze every rate index and value each type's debt at its last trusted
+    // price before splitting the vaults
+    let clock = Clock::from_account_info(clock_sysvar)?;
+    let mut frozen = Vec::new();
+    for triple in collateral_accounts.chunks_exact(3) {
+        let collateral_type = load_collateral_type(program_id, config_account, &triple[0])?;
+        let entry = freeze_collateral_type(
+            program_id,
+            config_account,
+            &triple[0],
+            collateral_type,
+            &triple[1],
+            &triple[2],
+            &mut state,
+            clock.unix_timestamp,
+        )?;
+
+        // Only debt needs a price; a type never priced since it was added or
+        // given a new feed has none, and its whole vault goes back to owners
+        if entry.debt > 0 && entry.collateral_type.last_oracle_price <= 0.0 {
+            return Err(StablecoinError::PriceUncertain.into());
+        }
+        frozen.push(entry);
+    }
+
+    create_settlements(program_id, config_account, admin, system_program, &state, &frozen, clock.unix_timestamp)?;
+
+    state.is_shut_down = 1;
+    state.is_emergency_shutdown = 1;
+    state.shutdown_collateral_types = state.collateral_types;
+    state.last_update_timestamp = clock.unix_timestamp;
+
+    ShutDown {
+        config: *config_account.key,
+        emergency: true,
+        collateral_types: state.shutdown_collateral_types,
+        total_supply: state.total_supply,
+    }
+    .emit()?;
+
+    msg!("Emergency shutdown with {} collateral types", frozen.len());
+    Ok(())
+}
+
+/// A collateral type frozen by a shutdown, awaiting its settlement.
+struct FrozenCollateral<'a, 'info> {
+    collateral_type_account: &'a AccountInfo<'info>,
+    settlement_account: &'a AccountInfo<'info>,
+    collateral_type: CollateralType,
+    /// Debt of all its positions at the frozen rate index.
+    debt: u64,
+    /// Vault collateral not owed to the fee collector.
+    available: u64,
+}
+
+/// Stops fee accrual and disables the collateral type at its final price.
+#[allow(clippy::too_many_arguments)]
+fn freeze_collateral_type<'a, 'info>(
+    program_id: &Pubkey,
+    config_account: &AccountInfo,
+    collateral_type_account: &'a AccountInfo<'info>,
+    mut collateral_type: CollateralType,
+    collateral_vault: &AccountInfo,
+    settlement_account: &'a AccountInfo<'info>,
+    state: &mut StablecoinState,
+    now: i64,
+) -> Result<FrozenCollateral<'a, 'info>, ProgramError> {
+    if *collateral_vault.key != collateral_type.vault || *collateral_vault.owner != spl_token::id() {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    let (vault_authority_pda, _) = Pubkey::find_program_address(
+        &[VAULT_AUTHORITY_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+    let vault = spl_token::state::Account::unpack(&collateral_vault.data.borrow())?;
+    if vault.owner != vault_authority_pda || vault.mint != collateral_type.collateral_mint {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    // Fees stop accruing once the rate index is frozen here
+    accrue_stability_fees(&mut collateral_type, state, now)?;
+    collateral_type.is_enabled = false;
+    collateral_type.store(&mut collateral_type_account.data.borrow_mut())?;
+
+    Ok(FrozenCollateral {
+        collateral_type_account,
+        settlement_account,
+        debt: denormalize_debt(collateral_type.total_normalized_debt, collateral_type.cumulative_rate_index),
+        available: vault.amount.saturating_sub(collateral_type.accrued_redemption_fees),
+        collateral_type,
+    })
+}
+
+/// Records each frozen collateral type's settlement: the collateral worth its
+/// covered debt at the final price is set aside for the holders, and the rest
+/// of the vault is owed back to the position owners.
+fn create_settlements<'info>(
+    program_id: &Pubkey,
+    config_account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    state: &StablecoinState,
+    frozen: &[FrozenCollateral<'_, 'info>],
+    now: i64,
+) -> ProgramResult {
+    for (index, entry) in frozen.iter().enumerate() {
+        if frozen[..index].iter().any(|prior| prior.collateral_type_account.key == entry.collateral_type_account.key) {
+            return Err(StablecoinError::IncompleteCollateralTypes.into());
+        }
+    }
+    // Distinct types were passed, so matching the count means all of them
+    if frozen.len() != state.collateral_types as usize {
+        return Err(StablecoinError::IncompleteCollateralTypes.into());
+    }
+
+    let total_debt = frozen.iter().map(|entry| entry.debt as u128).sum::<u128>();
+    let coverage = settlement_coverage(state.total_supply, total_debt, state.accrued_fees);
+
+    for entry in frozen {
+        let collateral_type = &entry.collateral_type;
+        let (settlement_pda, settlement_bump) =
+            find_settlement_address(program_id, config_account.key, &collateral_type.collateral_mint);
+        if settlement_pda != *entry.settlement_account.key {
+            return Err(ProgramError::InvalidSeeds);
+        }
+
+        let holder_collateral = if entry.debt == 0 {
+            0
+        } else {
+            stablecoin_to_collateral(
+                settled_debt(entry.debt, coverage),
+                collateral_type.last_oracle_price,
+                collateral_type.decimals,
+            )
+            .min(entry.available)
+        };
+        let collateral_per_token = if state.total_supply > 0 {
+            holder_collateral as f64 / state.total_supply as f64
+        } else {
+            0.0
+        };
+
+        let settlement = Settlement {
+            config: *config_account.key,
+            collateral_mint: collateral_type.collateral_mint,
+            vault: collateral_type.vault,
+            final_price: collateral_type.last_oracle_price,
+            collateral_per_token,
+            coverage,
+            holder_collateral,
+            owner_collateral: entry.available - holder_collateral,
+            settled_at: now,
+        };
+        create_program_account(
+            program_id,
+            payer,
+            entry.settlement_account,
+            system_program,
+            Settlement::LEN,
+            &[
+                SETTLEMENT_SEED,
+                config_account.key.as_ref(),
+                collateral_type.collateral_mint.as_ref(),
+                &[settlement_bump],
+            ],
+        )?;
+        settlement.store(&mut entry.settlement_account.data.borrow_mut())?;
+    }
+
+    Ok(())
+}
+
+/// The share of each position's debt owed to the holders. Stability fees
+/// accrued but never minted back no stablecoin, so they are left out, and
+/// holders are made whole at most: when positions owe more than the supply,
+/// each position only owes its share of the supply's face value.
+fn settlement_coverage(total_supply: u64, total_debt: u128, unminted_fees: u64) -> f64 {
+    if total_debt == 0 {
+        return 1.0;
+    }
+    let holder_debt = total_debt
+        .saturating_sub(unminted_fees as u128)
+        .min(total_supply as u128);
+    holder_debt as f64 / total_debt as f64
+}
+
+/// The part of `debt` settled against collateral at `coverage`.
+fn settled_debt(debt: u64, coverage: f64) -> u64 {
+    (debt as f64 * coverage) as u64
+}
+
+/// Settles a position at its collateral type's final price after either kind
+/// of shutdown: the collateral covering its share of the holders' claims stays
+/// with them and the rest is returned to the owner, after which the position
+/// is closed.
+fn process_settle_position(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let owner = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+    let collateral_type_account = next_account_info(accounts_iter)?;
+    let settlement_account = next_account_info(accounts_iter)?;
+    let collateral_vault = next_account_info(accounts_iter)?;
+    let vault_authority = next_account_info(accounts_iter)?;
+    let destination_collateral_account = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id || position_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // Verify position owner is signer
+    if !owner.is_signer {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    if load_state(program_id, config_account, state_account)?.is_shut_down == 0 {
+        return Err(StablecoinError::NotShutDown.into());
+    }
+
+    let position = Position::load(&position_account.data.borrow())?;
+    if position.owner != *owner.key || position.config != *config_account.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    let mut collateral_type = load_collateral_type(program_id, config_account, collateral_type_account)?;
+    let mut settlement: Settlement = load_config_account(program_id, config_account, settlement_account)?;
+    if collateral_type.collateral_mint != position.collateral_mint
+        || settlement.collateral_mint != position.collateral_mint
+    {
+        return Err(StablecoinError::InvalidMint.into());
+    }
+
+    let (vault_authority_pda, vault_bump) = Pubkey::find_program_address(
+        &[VAULT_AUTHORITY_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+    if vault_authority_pda != *vault_authority.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if *collateral_vault.key != settlement.vault || *destination_collateral_account.owner != spl_token::id() {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    let destination = spl_token::state::Account::unpack(&destination_collateral_account.data.borrow())?;
+    if destination.mint != settlement.collateral_mint {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    // The rate index was frozen at shutdown; only the covered part of the debt
+    // is owed, matching how the holder pool was sized
+    let debt = denormalize_debt(position.normalized_debt, collateral_type.cumulative_rate_index);
+    let owed = stablecoin_to_collateral(
+        settled_debt(debt, settlement.coverage),
+        settlement.final_price,
+        collateral_type.decimals,
+    )
+    .min(position.collateral_amount);
+    let excess = (position.collateral_amount - owed).min(settlement.owner_collateral);
+
+    if excess > 0 {
+        let transfer_ix = transfer(
+            &spl_token::id(),
+            collateral_vault.key,
+            destination_collateral_account.key,
+            vault_authority.key,
+            &[],
+            excess,
+        )?;
+
+        invoke_signed(
+            &transfer_ix,
+            &[
+                collateral_vault.clone(),
+                destination_collateral_account.clone(),
+                vault_authority.clone(),
+            ],
+            &[&[VAULT_AUTHORITY_SEED, config_account.key.as_ref(), &[vault_bump]]],
+        )?;
+    }
+
+    settlement.owner_collateral -= excess;
+    settlement.store(&mut settlement_account.data.borrow_mut())?;
+
+    collateral_type.total_normalized_debt = collateral_type.total_normalized_debt
+        .saturating_sub(position.normalized_debt);
+    collateral_type.debt = collateral_type.debt.saturating_sub(debt);
+    collateral_type.store(&mut collateral_type_account.data.borrow_mut())?;
+
+    close_program_account(position_account, owner)?;
+
+    msg!("Settled position with {} units of collateral returned", excess);
+    Ok(())
+}
+
+/// Burns `amount` after either kind of shutdown for the same share of every
+/// collateral type's remaining holder pool as `amount` is of the remaining
+/// supply.
+fn process_claim_settlement(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let token_mint = next_account_info(accounts_iter)?;
+    let user_token_account = next_account_info(accounts_iter)?;
+    let user = next_account_info(accounts_iter)?;
+    let vault_authority = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let blocklist_account = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let config = StablecoinConfig::load(&config_account.data.borrow())?;
+    let mut state = load_state_mut(program_id, config_account, state_account)?;
+
+    if state.is_shut_down == 0 {
+        return Err(StablecoinError::NotShutDown.into());
+    }
+
+    // Verify user is signer
+    if !user.is_signer {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    validate_token_program(token_program, &config)?;
+    validate_token_mint(token_mint, &config)?;
+    sync_total_supply(&mut state, token_mint)?;
+    let user_token = unpack_stablecoin_account(user_token_account, &config)?;
+    if user_token.owner != *user.key {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    ensure_not_blocked(program_id, config_account, blocklist_account, &[user.key])?;
+
+    if amount == 0 || amount > state.total_supply {
+        return Err(StablecoinError::InvalidAmount.into());
+    }
+
+    let (vault_authority_pda, vault_bump) = Pubkey::find_program_address(
+        &[VAULT_AUTHORITY_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+    if vault_authority_pda != *vault_authority.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Every settled collateral type follows as (settlement, vault, destination)
+    let collateral_accounts = accounts_iter.as_slice();
+    if collateral_accounts.len() != 3 * state.shutdown_collateral_types as usize {
+        return Err(StablecoinError::IncompleteCollateralTypes.into());
+    }
+
+    let mut claims = Vec::new();
+    for (index, triple) in collateral_accounts.chunks_exact(3).enumerate() {
+        let (settlement_account, collateral_vault, destination_collateral_account) =
+            (&triple[0], &triple[1], &triple[2]);
+        if collateral_accounts.chunks_exact(3).take(index).any(|prior| prior[0].key == settlement_account.key) {
+            return Err(StablecoinError::IncompleteCollateralTypes.into());
+        }
+
+        let mut settlement: Settlement = load_config_account(program_id, config_account, settlement_account)?;
+        if *collateral_vault.key != settlement.vault || *destination_collateral_account.owner != spl_token::id() {
+            return Err(StablecoinError::InvalidTokenAccount.into());
+        }
+
+        let destination = spl_token::state::Account::unpack(&destination_collateral_account.data.borrow())?;
+        if destination.mint != settlement.collateral_mint {
+            return Err(StablecoinError::InvalidTokenAccount.into());
+        }
+
+        let payout = shutdown_payout(settlement.holder_collateral, amount, state.total_supply);
+        settlement.holder_collateral -= payout;
+        claims.push((settlement_account, settlement, collateral_vault, destination_collateral_account, payout));
+    }
+
+    let burn_ix = burn(
+        token_program.key,
+        user_token_account.key,
+        token_mint.key,
+        user.key,
+        &[],
+        amount,
+    )?;
+
+    invoke(
+        &burn_ix,
+        &[
+            user_token_account.clone(),
+            token_mint.clone(),
+            user.clone(),
+        ],
+    )?;
+
+    for (settlement_account, settlement, collateral_vault, destination_collateral_account, payout) in claims {
+        if payout > 0 {
+            let transfer_ix = transfer(
+                &spl_token::id(),
+                collateral_vault.key,
+                destination_collateral_account.key,
+                vault_authority.key,
+                &[],
+                payout,
+            )?;
+
+            invoke_signed(
+                &transfer_ix,
+                &[
+                    collateral_vault.clone(),
+                    destination_collateral_account.clone(),
+                    vault_authority.clone(),
+                ],
+                &[&[VAULT_AUTHORITY_SEED, config_account.key.as_ref(), &[vault_bump]]],
+            )?;
+        }
+
+        settlement.store(&mut settlement_account.data.borrow_mut())?;
+
+        SettlementClaimed {
+            config: *config_account.key,
+            holder: *user.key,
+            collateral_mint: settlement.collateral_mint,
+            amount,
+            collateral_amount: payout,
+        }
+        .emit()?;
+    }
+
+    state.total_supply -= amount;
+
+    msg!("Redeemed {} tokens against the settled collateral", amount);
+    Ok(())
+}
+
+fn process_set_pause_flags(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    pause_flags: u8,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+    let state_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+    let mut state = load_state_mut(program_id, config_account, state_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    if pause_flags & !PAUSE_ALL != 0 {
+        return Err(StablecoinError::InvalidParameterAdjustment.into());
+    }
+
+    state.pause_flags = pause_flags;
+
+    Paused {
+        config: *config_account.key,
+        pause_flags,
+    }
+    .emit()?;
+
+    msg!("Pause flags set to {:#08b}", pause_flags);
+    Ok(())
+}
+
+/// Rewrites an existing transfer-hook account list to the current
+/// `extra_account_metas`, growing it if needed, so deployments created before
+/// an extra account was added pick it up.
+fn process_update_transfer_hook_accounts(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let roles_account = next_account_info(accounts_iter)?;
+    let admin = next_account_info(accounts_iter)?;
+    let extra_account_metas_account = next_account_info(accounts_iter)?;
+    let token_mint = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let config = StablecoinConfig::load(&config_account.data.borrow())?;
+    let roles: Roles = load_config_account(program_id, config_account, roles_account)?;
+
+    // Verify admin authority
+    if !admin.is_signer || roles.admin_authority != *admin.key {
+        return Err(StablecoinError::Unauthorized.into());
+    }
+
+    validate_token_mint(token_mint, &config)?;
+
+    let (extra_account_metas_pda, _) =
+        get_extra_account_metas_address_and_bump_seed(token_mint.key, program_id);
+    if extra_account_metas_pda != *extra_account_metas_account.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if extra_account_metas_account.owner != program_id || extra_account_metas_account.data_is_empty() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let metas = extra_account_metas(config_account.key)?;
+    let space = ExtraAccountMetaList::size_of(metas.len())?;
+    if space > extra_account_metas_account.data_len() {
+        let shortfall = Rent::get()?
+            .minimum_balance(space)
+            .saturating_sub(extra_account_metas_account.lamports());
+        if shortfall > 0 {
+            invoke(
+                &system_instruction::transfer(admin.key, extra_account_metas_account.key, shortfall),
+                &[admin.clone(), extra_account_metas_account.clone(), system_program.clone()],
+            )?;
+        }
+        extra_account_metas_account.realloc(space, false)?;
+    }
+
+    ExtraAccountMetaList::update::<ExecuteInstruction>(
+        &mut extra_account_metas_account.data.borrow_mut(),
+        &metas,
+    )?;
+
+    msg!("Updated transfer hook accounts for {}", token_mint.key);
+    Ok(())
+}
+
+/// Pays a collateral type's retained redemption fees out of its vault to the
+/// fee collector's collateral account. The destination is fixed by the
+/// treasury, so anyone may crank it, including during emergency settlement.
+fn process_collect_redemption_fees(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_account = next_account_info(accounts_iter)?;
+    let treasury_account = next_account_info(accounts_iter)?;
+    let collateral_type_account = next_account_info(accounts_iter)?;
+    let collateral_vault = next_account_info(accounts_iter)?;
+    let vault_authority = next_account_info(accounts_iter)?;
+    let fee_collector_collateral_account = next_account_info(accounts_iter)?;
+
+    if config_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let treasury: Treasury = load_config_account(program_id, config_account, treasury_account)?;
+    let mut collateral_type = load_collateral_type(program_id, config_account, collateral_type_account)?;
+
+    let fees = collateral_type.accrued_redemption_fees;
+    if fees == 0 {
+        return Err(StablecoinError::InsufficientStabilityFees.into());
+    }
+
+    let (vault_authority_pda, vault_bump) = Pubkey::find_program_address(
+        &[VAULT_AUTHORITY_SEED, config_account.key.as_ref()],
+        program_id,
+    );
+    if vault_authority_pda != *vault_authority.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if *collateral_vault.key != collateral_type.vault
+        || *fee_collector_collateral_account.owner != spl_token::id()
+    {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    let fee_collector_collateral = spl_token::state::Account::unpack(
+        &fee_collector_collateral_account.data.borrow(),
+    )?;
+    if fee_collector_collateral.owner != treasury.fee_collector
+        || fee_collector_collateral.mint != collateral_type.collateral_mint
+    {
+        return Err(StablecoinError::InvalidTokenAccount.into());
+    }
+
+    let transfer_ix = transfer(
+        &spl_token::id(),
+        collateral_vault.key,
+        fee_collector_collateral_account.key,
+        vault_authority.key,
+        &[],
+        fees,
+    )?;
+
+    invoke_signed(
+        &transfer_ix,
+        &[
+            collateral_vault.clone(),
+            fee_collector_collateral_account.clone(),
+            vault_authority.clone(),
+        ],
+        &[&[VAULT_AUTHORITY_SEED, config_account.key.as_ref(), &[vault_bump]]],
+    )?;
+
+    collateral_type.accrued_redemption_fees = 0;
+    collateral_type.store(&mut collateral_type_account.data.borrow_mut())?;
+
+    FeeCollected {
+        config: *config_account.key,
+        fee_collector: treasury.fee_collector,
+        amount: fees,
+        collateral_mint: Some(collateral_type.collateral_mint),
+    }
+    .emit()?;
+
+    msg!("Collected {} units of collateral in redemption fees", fees);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redemption_payout_scales_by_decimals() {
+        // 1 stablecoin (6 decimals) at 2 collateral per coin, 9-decimal collateral
+        let collateral = stablecoin_to_collateral(1_000_000, 2.0, 9);
+        assert_eq!(collateral, 2_000_000_000);
+        assert_eq!(collateral_to_stablecoin(collateral, 2.0, 9), 1_000_000);
+
+        // The fee is retained out of the collateral paid out
+        let fee = calculate_fee(collateral, 30).unwrap();
+        assert_eq!(collateral - fee, 1_994_000_000);
+    }
+
+    #[test]
+    fn test_redemption_floor_trips_on_underwater_positions() {
+        let mut data = vec![0u8; StablecoinState::LEN];
+        let state = StablecoinState::init(&mut data).unwrap();
+        state.redemption_price = 1.0;
+        state.min_redemption_rate = 0.98;
+
+        // A healthy position pays out exactly the redemption price's worth
+        assert_eq!(redemption_collateral(100, 2.0, 6, state, 1_000, 400), Ok(200));
+
+        // Down to the floor the position's own backing is paid instead
+        assert_eq!(redemption_collateral(100, 2.0, 6, state, 990, 500), Ok(198));
+
+        // Below it the redemption is refused
+        assert_eq!(
+            redemption_collateral(100, 2.0, 6, state, 950, 500),
+            Err(StablecoinError::BelowRedemptionPrice.into())
+        );
+
+        // A higher redemption price releases proportionally more collateral
+        state.redemption_price = 1.02;
+        assert_eq!(redemption_collateral(100, 2.0, 6, state, 1_000, 400), Ok(204));
+    }
+
+    #[test]
+    fn test_migration_rejects_headerless_configs() {
+        // The config layout the program first shipped with
+        #[derive(BorshSerialize)]
+        struct BaselineConfig {
+            name: String,
+            symbol: String,
+            icon_uri: String,
+            target_currency: String,
+            mint_authority: Pubkey,
+            oracle_pubkey: Pubkey,
+            total_supply: u64,
+            collateral_ratio: u64,
+            last_oracle_price: f64,
+            last_update_timestamp: i64,
+            admin_authority: Pubkey,
+            oracle_authority: Pubkey,
+            fee_collector: Pubkey,
+            is_paused: bool,
+            authorized_minters: Vec<Pubkey>,
+        }
+
+        let baseline = BaselineConfig {
+            name: "Stable".to_string(),
+            symbol: "STB".to_string(),
+            icon_uri: "https://example.com/icon.png".to_string(),
+            target_currency: "USD".to_string(),
+            mint_authority: Pubkey::new_unique(),
+            oracle_pubkey: Pubkey::new_unique(),
+            total_supply: 1_000_000,
+            collateral_ratio: 150,
+            last_oracle_price: 1.0,
+            last_update_timestamp: 1_700_000_000,
+            admin_authority: Pubkey::new_unique(),
+            oracle_authority: Pubkey::new_unique(),
+            fee_collector: Pubkey::new_unique(),
+            is_paused: false,
+            authorized_minters: vec![Pubkey::new_unique()],
+        };
+        let mut data = borsh::to_vec(&baseline).unwrap();
+        data.resize(1024, 0);
+        assert!(matches!(
+            load_legacy_config(&data),
+            Err(e) if e == StablecoinError::UnsupportedAccountVersion.into()
+        ));
+
+        let config = StablecoinConfig {
+            is_initialized: true,
+            mint_authority: Pubkey::new_unique(),
+            oracle_pubkey: Pubkey::new_unique(),
+            collateral_ratio: 150,
+            token_mint: Pubkey::new_unique(),
+            freeze_authority: Pubkey::new_unique(),
+            token_program: spl_token_2022::id(),
+            permanent_delegate: None,
+            kyc_mode: false,
+        };
+        assert!(matches!(load_legacy_config(&config.to_account_data().unwrap()), Ok(None)));
+    }
+
+    #[test]
+    fn test_liquidation_threshold_gates_redemptions_by_others() {
+        let collateral_type = CollateralType {
+            config: Pubkey::new_unique(),
+            collateral_mint: Pubkey::new_unique(),
+            oracle: Pubkey::new_unique(),
+            vault: Pubkey::new_unique(),
+            decimals: 6,
+            loan_to_value: 0.6,
+            liquidation_threshold: 0.8,
+            stability_fee_rate: 0.0,
+            cumulative_rate_index: 1.0,
+            last_fee_accrual_timestamp: 0,
+            total_normalized_debt: 0,
+            debt_ceiling: u64::MAX,
+            debt: 0,
+            accrued_redemption_fees: 0,
+            last_oracle_price: 2.0,
+            is_enabled: true,
+        };
+
+        // 2_000 collateral at 2 per coin is worth 1_000
+        assert!(!past_liquidation_threshold(600, 2_000, 2.0, &collateral_type));
+        assert!(!past_liquidation_threshold(800, 2_000, 2.0, &collateral_type));
+        assert!(past_liquidation_threshold(801, 2_000, 2.0, &collateral_type));
+
+        // A falling collateral price pushes the same position past it
+        assert!(past_liquidation_threshold(600, 2_000, 3.0, &collateral_type));
+    }
+
+    #[test]
+    fn test_state_zero_copy_round_trip() {
+        let mut data = vec![0u8; StablecoinState::LEN];
+        let state = StablecoinState::init(&mut data).unwrap();
+        state.total_supply = 42;
+        state.mint_fee_bps = 30;
+
+        let loaded = StablecoinState::load(&data).unwrap();
+        assert_eq!(loaded.total_supply, 42);
+        assert_eq!(loaded.mint_fee_bps, 30);
+
+        // Borsh accounts are not mistaken for the hot state
+        assert!(StablecoinState::load(&[0u8; StablecoinState::LEN]).is_err());
+    }
+
+    #[test]
+    fn test_pause_flags_are_independent() {
+        let mut data = vec![0u8; StablecoinState::LEN];
+        let state = StablecoinState::init(&mut data).unwrap();
+        state.pause_flags = PAUSE_MINT;
+
+        // Minting stays halted while holders can still redeem
+        assert!(state.ensure_not_paused(PAUSE_MINT | PAUSE_DEPOSIT).is_err());
+        assert!(state.ensure_not_paused(PAUSE_REDEEM).is_ok());
+        assert!(state.ensure_not_paused(PAUSE_TRANSFER_HOOK).is_ok());
+
+        state.pause_flags = PAUSE_ALL;
+        assert!(state.ensure_not_paused(PAUSE_WITHDRAW).is_err());
+    }
+
+    #[test]
+    fn test_load_state_checks_owner_type_and_config() {
+        let program_id = Pubkey::new_unique();
+        let config_key = Pubkey::new_unique();
+        let state_key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = vec![0u8; StablecoinState::LEN];
+        StablecoinState::init(&mut data).unwrap().config = config_key;
+
+        let config_account = AccountInfo::new(
+            &config_key, false, false, &mut lamports, &mut [], &program_id, false, 0,
+        );
+        let mut state_lamports = 0;
+        let state_account = AccountInfo::new(
+            &state_key, false, true, &mut state_lamports, &mut data, &program_id, false, 0,
+        );
+        assert_eq!(load_state(&program_id, &config_account, &state_account).unwrap().config, config_key);
+        load_state_mut(&program_id, &config_account, &state_account).unwrap().total_supply = 7;
+        assert_eq!(load_state(&program_id, &config_account, &state_account).unwrap().total_supply, 7);
+
+        // Another config's state is rejected
+        let other_config = Pubkey::new_unique();
+        let mut other_lamports = 0;
+        let other_account = AccountInfo::new(
+            &other_config, false, false, &mut other_lamports, &mut [], &program_id, false, 0,
+        );
+        assert_eq!(
+            load_state(&program_id, &other_account, &state_account).err(),
+            Some(ProgramError::InvalidSeeds)
+        );
+
+        // The loader's own error surfaces for a wrong account type
+        state_account.data.borrow_mut()[0] ^= 0xff;
+        assert_eq!(
+            load_state_mut(&program_id, &config_account, &state_account).err(),
+            Some(StablecoinError::InvalidAccountType.into())
+        );
+    }
+
+    #[test]
+    fn test_settlement_splits_uncovered_debt_with_owners() {
+        // Fully backed supply: every position owes its whole debt
+        assert_eq!(settlement_coverage(1_000, 800, 0), 1.0);
+        assert_eq!(settled_debt(800, 1.0), 800);
+
+        // Unminted stability fees are not owed to the holders
+        let coverage = settlement_coverage(1_000, 1_000, 200);
+        assert_eq!(settled_debt(1_000, coverage), 800);
+
+        // Positions owe twice the supply, so each owes half its debt
+        let coverage = settlement_coverage(500, 1_000, 0);
+        assert_eq!(coverage, 0.5);
+
+        // A position owing 600 against 1000 units at 1 collateral per coin
+        // (6-decimal collateral) keeps everything but the covered 300
+        let owed = stablecoin_to_collateral(settled_debt(600, coverage), 1.0, 6);
+        assert_eq!(owed, 300);
+        assert_eq!(1_000 - owed, 700);
+    }
+
+    #[test]
+    fn test_shutdown_payouts_drain_the_vault() {
+        // Three equal redemptions of a 3-token supply against 100 units
+        let mut available = 100;
+        let mut supply = 3;
+        let mut paid = Vec::new();
+        while supply > 0 {
+            let payout = shutdown_payout(available, 1, supply);
+            paid.push(payout);
+            available -= payout;
+            supply -= 1;
+        }
+
+        // Rounding never favours early redeemers, and nothing is left behind
+        assert_eq!(paid, vec![33, 33, 34]);
+        assert_eq!(available, 0);
+    }
+
+    #[test]
+    fn test_shutdown_returns_overcollateralization_to_owners() {
+        // Two positions owing 100 each against 300 and 200 units of 6-decimal
+        // collateral worth 1 per coin, with the whole 200 supply outstanding
+        let positions = [(300u64, 100u64), (200, 100)];
+        let available = 500;
+        let supply = 200;
+        let coverage = settlement_coverage(supply, 200, 0);
+        let holder_collateral = stablecoin_to_collateral(settled_debt(200, coverage), 1.0, 6).min(available);
+        let owner_collateral = available - holder_collateral;
+
+        // Holders redeeming the whole supply get only the debt's worth
+        let first = shutdown_payout(holder_collateral, 150, supply);
+        let last = shutdown_payout(holder_collateral - first, 50, supply - 150);
+        assert_eq!((first, last), (150, 50));
+
+        // and each owner gets back what their position held beyond its debt
+        let excess: Vec<u64> = positions
+            .iter()
+            .map(|&(collateral, debt)| {
+                collateral - stablecoin_to_collateral(settled_debt(debt, coverage), 1.0, 6).min(collateral)
+            })
+            .collect();
+        assert_eq!(excess, vec![200, 100]);
+        assert_eq!(excess.iter().sum::<u64>(), owner_collateral);
+    }
+
+    #[test]
+    fn test_seize_requires_compliance_and_permanent_delegate() {
+        let program_id = Pubkey::new_unique();
+        let config_key = Pubkey::new_unique();
+        let compliance_key = Pubkey::new_unique();
+        let mut config_data = StablecoinConfig {
+            is_initialized: true,
+            mint_authority: Pubkey::new_unique(),
+            oracle_pubkey: Pubkey::new_unique(),
+            collateral_ratio: 150,
+            token_mint: Pubkey::new_unique(),
+            freeze_authority: Pubkey::new_unique(),
+            token_program: spl_token::id(),
+            permanent_delegate: None,
+            kyc_mode: false,
+        }
+        .to_account_data()
+        .unwrap();
+        let mut roles_data = Roles {
+            config: config_key,
+            admin_authority: Pubkey::new_unique(),
+            oracle_authority: Pubkey::new_unique(),
+            compliance_authority: compliance_key,
+            authorized_minters: Vec::new(),
+        }
+        .to_account_data()
+        .unwrap();
+        let mut treasury_data = Treasury {
+            config: config_key,
+            fee_collector: Pubkey::new_unique(),
+            wallet: Pubkey::new_unique(),
+            fee_exempt_accounts: Vec::new(),
+        }
+        .to_account_data()
+        .unwrap();
+
+        let keys: Vec<Pubkey> = (0..7).map(|_| Pubkey::new_unique()).collect();
+        let (roles_key, treasury_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut lamports = [0u64; 11];
+        let [config_lamports, roles_lamports, treasury_lamports, compliance_lamports, rest @ ..] = &mut lamports;
+        let mut rest_data = [[0u8; 0]; 7];
+        let mut accounts = vec![
+            AccountInfo::new(&config_key, false, false, config_lamports, &mut config_data, &program_id, false, 0),
+            AccountInfo::new(&roles_key, false, false, roles_lamports, &mut roles_data, &program_id, false, 0),
+            AccountInfo::new(&treasury_key, false, false, treasury_lamports, &mut treasury_data, &program_id, false, 0),
+            AccountInfo::new(&compliance_key, false, false, compliance_lamports, &mut [], &program_id, false, 0),
+        ];
+        for ((key, lamports), data) in keys.iter().zip(rest.iter_mut()).zip(rest_data.iter_mut()) {
+            accounts.push(AccountInfo::new(key, false, true, lamports, data, &program_id, false, 0));
+        }
+
+        // Only the compliance authority's signature can seize
+        assert_eq!(process_seize(&program_id, &accounts, 1).err(), Some(StablecoinError::Unauthorized.into()));
+
+        // Classic SPL mints have no permanent delegate to burn with
+        accounts[3].is_signer = true;
+        assert_eq!(
+            process_seize(&program_id, &accounts, 1).err(),
+            Some(StablecoinError::ClawbackUnsupported.into())
+        );
+    }
+}
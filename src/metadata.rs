@@ -0,0 +1,225 @@
+use mpl_token_metadata::{
+    accounts::Metadata,
+    instructions::{
+        CreateMetadataAccountV3Cpi, CreateMetadataAccountV3CpiAccounts,
+        CreateMetadataAccountV3InstructionArgs, UpdateMetadataAccountV2Cpi,
+        UpdateMetadataAccountV2CpiAccounts, UpdateMetadataAccountV2InstructionArgs,
+    },
+    types::DataV2,
+};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_instruction,
+    sysvar::{rent::Rent, Sysvar},
+};
+use spl_token_2022::{
+    extension::{metadata_pointer, BaseStateWithExtensions, StateWithExtensions},
+    state::Mint,
+};
+use spl_token_metadata_interface::{
+    instruction::{initialize, update_field},
+    state::{Field, TokenMetadata},
+};
+
+/// Returns the TLV length the token-metadata extension will need for these fields.
+pub fn token_metadata_len(
+    mint: &Pubkey,
+    update_authority: &Pubkey,
+    name: &str,
+    symbol: &str,
+    uri: &str,
+) -> Result<usize, ProgramError> {
+    let metadata = TokenMetadata {
+        update_authority: Some(*update_authority).try_into()?,
+        mint: *mint,
+        name: name.to_string(),
+        symbol: symbol.to_string(),
+        uri: uri.to_string(),
+        additional_metadata: vec![],
+    };
+    metadata.tlv_size_of()
+}
+
+/// Points the mint's metadata at the mint itself. Must run before the mint is initialized.
+pub fn initialize_metadata_pointer<'a>(
+    token_program: &AccountInfo<'a>,
+    token_mint: &AccountInfo<'a>,
+    authority: &Pubkey,
+) -> ProgramResult {
+    invoke(
+        &metadata_pointer::instruction::initialize(
+            token_program.key,
+            token_mint.key,
+            Some(*authority),
+            Some(*token_mint.key),
+        )?,
+        std::slice::from_ref(token_mint),
+    )
+}
+
+/// Writes the token-metadata extension onto an initialized Token-2022 mint.
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_token_metadata<'a>(
+    token_program: &AccountInfo<'a>,
+    token_mint: &AccountInfo<'a>,
+    mint_authority: &AccountInfo<'a>,
+    signer_seeds: &[&[u8]],
+    name: String,
+    symbol: String,
+    uri: String,
+) -> ProgramResult {
+    invoke_signed(
+        &initialize(
+            token_program.key,
+            token_mint.key,
+            mint_authority.key,
+            token_mint.key,
+            mint_authority.key,
+            name,
+            symbol,
+            uri,
+        ),
+        &[token_mint.clone(), mint_authority.clone()],
+        &[signer_seeds],
+    )
+}
+
+/// Rewrites name, symbol and uri on a Token-2022 mint, topping up rent from
+/// `payer` when the metadata grows.
+#[allow(clippy::too_many_arguments)]
+pub fn update_token_metadata<'a>(
+    token_program: &AccountInfo<'a>,
+    token_mint: &AccountInfo<'a>,
+    update_authority: &AccountInfo<'a>,
+    payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    signer_seeds: &[&[u8]],
+    name: String,
+    symbol: String,
+    uri: String,
+) -> ProgramResult {
+    let (current_len, new_len) = {
+        let data = token_mint.data.borrow();
+        let mint = StateWithExtensions::<Mint>::unpack(&data)?;
+        let mut metadata = mint.get_variable_len_extension::<TokenMetadata>()?;
+        let current_len = metadata.tlv_size_of()?;
+        metadata.update(Field::Name, name.clone());
+        metadata.update(Field::Symbol, symbol.clone());
+        metadata.update(Field::Uri, uri.clone());
+        (current_len, metadata.tlv_size_of()?)
+    };
+
+    if new_len > current_len {
+        let required_lamports = Rent::get()?
+            .minimum_balance(token_mint.data_len() + new_len - current_len);
+        let shortfall = required_lamports.saturating_sub(token_mint.lamports());
+        if shortfall > 0 {
+            invoke(
+                &system_instruction::transfer(payer.key, token_mint.key, shortfall),
+                &[payer.clone(), token_mint.clone(), system_program.clone()],
+            )?;
+        }
+    }
+
+    for (field, value) in [(Field::Name, name), (Field::Symbol, symbol), (Field::Uri, uri)] {
+        invoke_signed(
+            &update_field(token_program.key, token_mint.key, update_authority.key, field, value),
+            &[token_mint.clone(), update_authority.clone()],
+            &[signer_seeds],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Verifies the Metaplex program and that `metadata_account` is the metadata PDA for `mint`.
+pub fn validate_metaplex_accounts(
+    metadata_account: &AccountInfo,
+    token_metadata_program: &AccountInfo,
+    mint: &Pubkey,
+) -> ProgramResult {
+    if *token_metadata_program.key != mpl_token_metadata::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if *metadata_account.key != Metadata::find_pda(mint).0 {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(())
+}
+
+fn metaplex_data(name: String, symbol: String, uri: String) -> DataV2 {
+    DataV2 {
+        name,
+        symbol,
+        uri,
+        seller_fee_basis_points: 0,
+        creators: None,
+        collection: None,
+        uses: None,
+    }
+}
+
+/// Creates the Metaplex metadata account for a classic SPL mint, with the mint
+/// authority PDA as both signer and update authority.
+#[allow(clippy::too_many_arguments)]
+pub fn create_metaplex_metadata<'a>(
+    token_metadata_program: &AccountInfo<'a>,
+    metadata_account: &AccountInfo<'a>,
+    token_mint: &AccountInfo<'a>,
+    mint_authority: &AccountInfo<'a>,
+    payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    signer_seeds: &[&[u8]],
+    name: String,
+    symbol: String,
+    uri: String,
+) -> ProgramResult {
+    CreateMetadataAccountV3Cpi::new(
+        token_metadata_program,
+        CreateMetadataAccountV3CpiAccounts {
+            metadata: metadata_account,
+            mint: token_mint,
+            mint_authority,
+            payer,
+            update_authority: (mint_authority, true),
+            system_program,
+            rent: None,
+        },
+        CreateMetadataAccountV3InstructionArgs {
+            data: metaplex_data(name, symbol, uri),
+            is_mutable: true,
+            collection_details: None,
+        },
+    )
+    .invoke_signed(&[signer_seeds])
+}
+
+/// Rewrites name, symbol and uri on an existing Metaplex metadata account.
+pub fn update_metaplex_metadata<'a>(
+    token_metadata_program: &AccountInfo<'a>,
+    metadata_account: &AccountInfo<'a>,
+    update_authority: &AccountInfo<'a>,
+    signer_seeds: &[&[u8]],
+    name: String,
+    symbol: String,
+    uri: String,
+) -> ProgramResult {
+    UpdateMetadataAccountV2Cpi::new(
+        token_metadata_program,
+        UpdateMetadataAccountV2CpiAccounts {
+            metadata: metadata_account,
+            update_authority,
+        },
+        UpdateMetadataAccountV2InstructionArgs {
+            data: Some(metaplex_data(name, symbol, uri)),
+            new_update_authority: None,
+            primary_sale_happened: None,
+            is_mutable: None,
+        },
+    )
+    .invoke_signed(&[signer_seeds])
+}
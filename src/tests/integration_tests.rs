@@ -11,9 +11,6 @@ async fn test_full_stablecoin_flow() {
     let mut context = TestContext::new().await;
     let mut banks_client = program_test.start_with_context().await;
 
-    // Initialize program
-    context.initialize(&mut banks_client).await.unwrap();
-
     // Test initialization
     let init_ix = StablecoinInstruction::Initialize {
         name: "Test Coin".to_string(),
@@ -41,6 +38,7 @@ async fn test_full_stablecoin_flow() {
     let mint_amount = 1000;
     let mint_ix = StablecoinInstruction::Mint {
         amount: mint_amount,
+        min_out: 0,
     };
 
     let transaction = Transaction::new_signed_with_payer(
@@ -63,6 +61,7 @@ async fn test_full_stablecoin_flow() {
     let redeem_amount = 500;
     let redeem_ix = StablecoinInstruction::Redeem {
         amount: redeem_amount,
+        min_out: 0,
     };
 
     let transaction = Transaction::new_signed_with_payer(
@@ -102,9 +101,6 @@ async fn test_authority_management() {
     let mut context = TestContext::new().await;
     let mut banks_client = program_test.start_with_context().await;
 
-    // Initialize program
-    context.initialize(&mut banks_client).await.unwrap();
-
     // Test adding a new minter
     let new_minter = Keypair::new();
     let add_minter_ix = StablecoinInstruction::AddMinter {
@@ -36,8 +36,8 @@ impl TestContext {
         let mint_authority = Keypair::new();
         let oracle_authority = Keypair::new();
         let user = Keypair::new();
-        let config = Pubkey::new_unique();
         let token_mint = Pubkey::new_unique();
+        let (config, _) = find_config_address(&program_id, &token_mint);
         let oracle = Pubkey::new_unique();
 
         Self {
@@ -51,26 +51,4 @@ impl TestContext {
             oracle,
         }
     }
-
-    pub async fn initialize(&self, banks_client: &mut BanksClient) -> Result<(), BanksClientError> {
-        let rent = banks_client.get_rent().await?;
-        let config_size = 1000; // Adjust based on actual size needed
-
-        let ix = system_instruction::create_account(
-            &self.admin.pubkey(),
-            &self.config,
-            rent.minimum_balance(config_size),
-            config_size as u64,
-            &self.program_id,
-        );
-
-        let transaction = Transaction::new_signed_with_payer(
-            &[ix],
-            Some(&self.admin.pubkey()),
-            &[&self.admin],
-            banks_client.get_latest_blockhash().await?,
-        );
-
-        banks_client.process_transaction(transaction).await
-    }
 } 
\ No newline at end of file
@@ -5,7 +5,7 @@ proptest! {
     #[test]
     fn test_mint_amount_validation(amount in 0..=u64::MAX) {
         let program_id = Pubkey::new_unique();
-        let result = process_mint(&program_id, &vec![], amount);
+        let result = process_mint(&program_id, &vec![], amount, 0);
         
         if amount > MINT_LIMIT {
             assert!(matches!(result, Err(ProgramError::Custom(_))));
@@ -73,6 +73,7 @@ mod tests {
             &program_id,
             &vec![],  // Empty accounts for this test
             MINT_LIMIT + 1,
+            0,
         );
         assert!(matches!(result, Err(ProgramError::Custom(_))));
 
@@ -84,6 +85,7 @@ mod tests {
             &program_id,
             &vec![],  // Empty accounts for this test
             1000,
+            0,
         );
         assert!(matches!(result, Err(ProgramError::Custom(_))));
     }
@@ -102,4 +104,4 @@ mod tests {
         );
         assert!(matches!(result, Err(ProgramError::Custom(_))));
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,141 @@
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, seeds::Seed};
+use crate::STATE_SEED;
+
+pub const ALLOWLIST_SEED: &[u8] = b"allowlist";
+
+// Account indices in the transfer-hook `Execute` instruction
+const SOURCE_ACCOUNT_INDEX: u8 = 0;
+const DESTINATION_ACCOUNT_INDEX: u8 = 2;
+const CONFIG_ACCOUNT_INDEX: u8 = 5;
+// Owner field offset within an SPL token account
+const TOKEN_ACCOUNT_OWNER_OFFSET: u8 = 32;
+
+/// Extra accounts Token-2022 must pass to `Execute`: the config, the allowlist
+/// entries of the source and destination owners, and the hot state carrying
+/// the pause flags.
+pub fn extra_account_metas(config: &Pubkey) -> Result<Vec<ExtraAccountMeta>, ProgramError> {
+    let allowlist_entry_for = |account_index| {
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal { bytes: ALLOWLIST_SEED.to_vec() },
+                Seed::AccountKey { index: CONFIG_ACCOUNT_INDEX },
+                Seed::AccountData {
+                    account_index,
+                    data_index: TOKEN_ACCOUNT_OWNER_OFFSET,
+                    length: 32,
+                },
+            ],
+            false,
+            false,
+        )
+    };
+
+    Ok(vec![
+        ExtraAccountMeta::new_with_pubkey(config, false, false)?,
+        allowlist_entry_for(SOURCE_ACCOUNT_INDEX)?,
+        allowlist_entry_for(DESTINATION_ACCOUNT_INDEX)?,
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal { bytes: STATE_SEED.to_vec() },
+                Seed::AccountKey { index: CONFIG_ACCOUNT_INDEX },
+            ],
+            false,
+            false,
+        )?,
+    ])
+}
+
+pub fn find_allowlist_entry_address(program_id: &Pubkey, config: &Pubkey, wallet: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ALLOWLIST_SEED, config.as_ref(), wallet.as_ref()], program_id)
+}
+
+/// Returns whether `entry_account` is the live allowlist entry for `wallet`.
+pub fn is_allowlisted(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    wallet: &Pubkey,
+    entry_account: &AccountInfo,
+) -> bool {
+    let (entry_address, _) = find_allowlist_entry_address(program_id, config, wallet);
+    *entry_account.key == entry_address
+        && entry_account.owner == program_id
+        && !entry_account.data_is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find_state_address;
+
+    fn token_account_data(owner: &Pubkey) -> Vec<u8> {
+        let mut data = vec![0u8; 165];
+        let offset = TOKEN_ACCOUNT_OWNER_OFFSET as usize;
+        data[offset..offset + 32].copy_from_slice(owner.as_ref());
+        data
+    }
+
+    #[test]
+    fn test_extra_account_metas_resolve_to_allowlist_entries() {
+        let program_id = Pubkey::new_unique();
+        let config = Pubkey::new_unique();
+        let (source_owner, destination_owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (source_data, destination_data) =
+            (token_account_data(&source_owner), token_account_data(&destination_owner));
+
+        // Execute's fixed accounts: source, mint, destination, authority,
+        // validation account, then the config as the first extra account
+        let keys: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).chain([config]).collect();
+        let account = |index: usize| {
+            let data = match index as u8 {
+                SOURCE_ACCOUNT_INDEX => Some(source_data.as_slice()),
+                DESTINATION_ACCOUNT_INDEX => Some(destination_data.as_slice()),
+                _ => None,
+            };
+            keys.get(index).map(|key| (key, data))
+        };
+
+        let resolved: Vec<Pubkey> = extra_account_metas(&config)
+            .unwrap()
+            .iter()
+            .map(|meta| meta.resolve(&[], &program_id, account).unwrap().pubkey)
+            .collect();
+        assert_eq!(
+            resolved,
+            vec![
+                config,
+                find_allowlist_entry_address(&program_id, &config, &source_owner).0,
+                find_allowlist_entry_address(&program_id, &config, &destination_owner).0,
+                find_state_address(&program_id, &config).0,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_allowlisted_requires_live_entry() {
+        let program_id = Pubkey::new_unique();
+        let config = Pubkey::new_unique();
+        let wallet = Pubkey::new_unique();
+        let (entry_key, _) = find_allowlist_entry_address(&program_id, &config, &wallet);
+        let mut lamports = 0;
+        let mut data = vec![1u8; 8];
+        let entry = AccountInfo::new(&entry_key, false, false, &mut lamports, &mut data, &program_id, false, 0);
+        assert!(is_allowlisted(&program_id, &config, &wallet, &entry));
+
+        // Another wallet's entry does not count
+        assert!(!is_allowlisted(&program_id, &config, &Pubkey::new_unique(), &entry));
+
+        // Neither does a closed or foreign-owned account at the entry address
+        let mut closed_lamports = 0;
+        let closed = AccountInfo::new(&entry_key, false, false, &mut closed_lamports, &mut [], &program_id, false, 0);
+        assert!(!is_allowlisted(&program_id, &config, &wallet, &closed));
+
+        let other_owner = Pubkey::new_unique();
+        let mut foreign_lamports = 0;
+        let mut foreign_data = vec![1u8; 8];
+        let foreign = AccountInfo::new(
+            &entry_key, false, false, &mut foreign_lamports, &mut foreign_data, &other_owner, false, 0,
+        );
+        assert!(!is_allowlisted(&program_id, &config, &wallet, &foreign));
+    }
+}